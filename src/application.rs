@@ -1,33 +1,99 @@
-use crate::clipboard::Clipboard;
-use crate::editor::{Editor, Vector2};
-use crate::renderer::{RenderOpts, Renderer, StringRenderer};
+use crate::buffer::BufferList;
+use crate::clipboard::{Clipboard, KillRing, RegisterPrompt};
+use crate::command_line::{self, CommandLine, Typed};
+use crate::config::Setting;
+use crate::editor::{Editor, Highlight, Vector2};
+use crate::keymap::{self, Command, Keymap};
+use crate::layout::{self, Layout, Pane, Split};
+use crate::macros::{MacroPrompt, Macros};
+use crate::renderer::{self, Draw, Gutter, Rect, Renderer, ScrollOff, StyledRenderer};
+use crate::screen::Screen;
+use crate::search::{Direction, IncrementalSearch, Query, ReplaceSession, ReplaceStep};
+use crate::status::StatusLine;
+use crate::unicode;
+use crate::vi::{Mode, Vi};
+use crate::wrap;
 
-use crossterm::{cursor::MoveTo, terminal::{self}, ExecutableCommand, QueueableCommand};
+use std::time::{Duration, Instant};
+
+use crossterm::{cursor::MoveTo, terminal::{self}, ExecutableCommand};
 
 use crossterm::terminal::{ClearType, Clear};
 
-use std::io::Write;
-use std::borrow::Borrow;
 use crossterm::event::{EnableMouseCapture, MouseEvent, KeyEvent, read, Event, MouseButton};
 use crossterm::event::KeyCode;
 use crossterm::event::KeyModifiers;
-use crossterm::style;
 use crossterm::style::Color;
 
+/// background of the match the cursor is on
+const CURRENT_MATCH_COLOR: Color = Color::DarkYellow;
+/// background of every other visible match
+const MATCH_COLOR: Color = Color::DarkGrey;
+/// background of the active buffer in the tab line
+const ACTIVE_TAB_COLOR: Color = Color::DarkBlue;
+/// background of the status line
+const STATUS_LINE_COLOR: Color = Color::DarkGrey;
+/// screen rows above the editor, taken up by the tab line
+const TAB_LINE_HEIGHT: u16 = 1;
+/// share of the screen a pane grows or shrinks by at a time
+const RESIZE_STEP: f32 = 0.05;
+/// the longest time between the clicks of a double or triple click
+const MULTI_CLICK_TIME: Duration = Duration::from_millis(400);
+
 /// handles the main application logic
 pub struct Application<T>
 where
     T: Clipboard,
 {
-    pub editor: Editor,
-    pub clipboard: T,
-    pub render_opts: RenderOpts,
+    /// the open buffers, input goes to the active one
+    pub buffers: BufferList,
+    /// the clipboard, along with the earlier copies and the named registers
+    pub clipboard: KillRing<T>,
     pub exit: bool,
     pub log: String,
-
-    // hint to only render a particular line
-    render_line_hint: Option<i32>,
-    render_break_line_hint: bool,
+    /// incremental search in progress, receives all key events while active
+    pub search: Option<IncrementalSearch>,
+    /// find and replace in progress, receives all key events while active
+    pub replace: Option<ReplaceSession>,
+    /// the frame on the terminal, used to only redraw what changed
+    screen: Screen,
+    // true after ^W was pressed on a buffer with unsaved changes
+    confirm_close: bool,
+    /// the split panes, each showing a buffer
+    pub layout: Layout,
+    /// index of the pane receiving input, which shows the active buffer
+    focus: usize,
+    // the area of each pane as of the last render
+    pane_rects: Vec<Rect>,
+    /// the line numbers drawn to the left of every pane
+    pub gutter: Gutter,
+    /// wrap long lines in every pane instead of scrolling sideways
+    pub wrap: bool,
+    /// the space kept between the cursor and the edges of the view as it scrolls
+    pub scrolloff: ScrollOff,
+    /// the command run by each key
+    pub keymap: Keymap,
+    /// vi style modal editing, which gets keys before the key bindings when it is on
+    pub vi: Option<Vi>,
+    /// the command being typed, receives all key events while active
+    pub command_line: Option<CommandLine>,
+    /// the lines run from the command line, oldest first
+    pub command_history: Vec<String>,
+    /// the distance between tab stops when drawing tabs
+    pub tab_width: usize,
+    /// the recorded keyboard macros
+    pub macros: Macros,
+    // set when the next key names the register of a macro to record or play
+    macro_prompt: Option<MacroPrompt>,
+    // the macros being played, innermost last. nothing is drawn until they finish
+    playing: Vec<char>,
+    // when and where the mouse was last clicked, and how many clicks in a row that made
+    last_click: Option<(Instant, Vector2, usize)>,
+    // set when the next key names the register to copy to or paste from
+    register_prompt: Option<RegisterPrompt>,
+    // the text just pasted, and how many copies ago it was copied. it can be swapped for an
+    // earlier copy by the next command
+    pasted: Option<(Option<usize>, String)>,
 }
 
 impl<T> Application<T>
@@ -36,13 +102,92 @@ where
 {
     pub fn new(editor: Editor, clipboard: T) -> Application<T> {
         Application {
-            editor,
-            clipboard,
-            render_opts: RenderOpts::default(),
+            buffers: BufferList::new(editor),
+            clipboard: KillRing::new(clipboard),
             exit: false,
             log: String::new(),
-            render_line_hint: None,
-            render_break_line_hint: false,
+            search: None,
+            replace: None,
+            screen: Screen::new(0, 0),
+            confirm_close: false,
+            layout: Layout::new(Pane::default()),
+            focus: 0,
+            pane_rects: Vec::new(),
+            gutter: Gutter::default(),
+            wrap: false,
+            scrolloff: ScrollOff::default(),
+            keymap: Keymap::default(),
+            vi: None,
+            command_line: None,
+            command_history: Vec::new(),
+            tab_width: unicode::TAB_WIDTH,
+            macros: Macros::new(),
+            macro_prompt: None,
+            playing: Vec::new(),
+            last_click: None,
+            register_prompt: None,
+            pasted: None,
+        }
+    }
+
+    /// open another buffer and show it in the focused pane
+    pub fn open(&mut self, mut editor: Editor) {
+        editor.set_tab_width(self.tab_width);
+        self.buffers.open(editor);
+        self.show_active_buffer();
+    }
+
+    /// make the buffer at `index` active and show it in the focused pane
+    pub fn select_buffer(&mut self, index: usize) {
+        self.buffers.select(index);
+        self.show_active_buffer();
+    }
+
+    /// show the active buffer in the focused pane
+    fn show_active_buffer(&mut self) {
+        let active = self.buffers.active_index();
+        if let Some(pane) = self.layout.pane_mut(self.focus) {
+            pane.buffer = active;
+        }
+    }
+
+    /// move input to the pane at `index`, making its buffer active.
+    /// the view of the pane losing focus is kept in the pane
+    pub fn focus_pane(&mut self, index: usize) {
+        let view = self.buffers.render_opts();
+        if let Some(pane) = self.layout.pane_mut(self.focus) {
+            pane.render_opts = view;
+        }
+        self.load_pane(index);
+    }
+
+    /// make the pane at `index` focused, restoring its view
+    fn load_pane(&mut self, index: usize) {
+        if let Some(pane) = self.layout.pane(index).copied() {
+            self.focus = index;
+            self.buffers.select(pane.buffer);
+            *self.buffers.render_opts_mut() = pane.render_opts;
+        }
+    }
+
+    /// split the focused pane, the new pane shows the same buffer and takes the focus
+    pub fn split_pane(&mut self, direction: Split) {
+        let pane = Pane {
+            buffer: self.buffers.active_index(),
+            render_opts: self.buffers.render_opts(),
+        };
+        if self.layout.split(self.focus, direction, pane) {
+            self.focus_pane(self.focus + 1);
+        }
+    }
+
+    /// close the focused pane, unless it is the only one
+    pub fn close_pane(&mut self) {
+        if self.layout.close(self.focus).is_some() {
+            // the focus goes to the pane before the closed one
+            self.load_pane(self.focus.saturating_sub(1));
+        } else {
+            self.log = "can't close the last pane".to_string();
         }
     }
 
@@ -63,14 +208,23 @@ where
                 break Ok(());
             }
 
-            self.process_event();
+            if let Err(e) = self.process_event() {
+                self.log = format!("error reading event: {}", e);
+            }
             // thread::sleep(std::time::Duration::from_millis(50));
         }
     }
 
     pub fn process_event(&mut self) -> crossterm::Result<()> {
         match read()? {
-            Event::Key(event) => self.process_key_event(event),
+            Event::Key(event) => {
+                // the keys which start and stop recording aren't part of the macro
+                let recording = self.macros.recording().is_some();
+                self.process_key_event(event);
+                if recording && self.macros.recording().is_some() {
+                    self.macros.record(event);
+                }
+            }
             Event::Mouse(event) => self.process_mouse_event(event),
             _ => {}
         }
@@ -78,234 +232,1270 @@ where
         Ok(())
     }
 
+    /// count a click at `location` towards a double or triple click, returning how many clicks
+    /// in a row it makes. a fourth click starts over
+    fn count_click(&mut self, location: Vector2) -> usize {
+        let now = Instant::now();
+        let clicks = match self.last_click {
+            Some((time, at, clicks)) if at == location && now - time <= MULTI_CLICK_TIME => {
+                clicks % 3 + 1
+            }
+            _ => 1,
+        };
+        self.last_click = Some((now, location, clicks));
+        clicks
+    }
+
     pub fn process_mouse_event(&mut self, event: MouseEvent) {
         self.log = "Processing mouse event".to_string();
 
         // convert screen coordinates into editor coordinates
         macro_rules! to_editor_coords {
             ($x:ident, $y:ident) => {{
-                let Vector2(x2, y2) = self.render_opts.view.location;
-                ($x + x2, $y + y2)
+                let opts = self.buffers.render_opts();
+                let editor = self.buffers.editor();
+                let origin = self.pane_rects[self.focus].location;
+                let gutter = opts.gutter_width(editor);
+                // a click on the gutter goes to the first column in view
+                let position = Vector2(($x - origin.x() - gutter).max(0), $y - origin.y());
+                if opts.wrap {
+                    let Vector2(x, y) = wrap::location_at(editor, opts.view, position);
+                    (x, y)
+                } else {
+                    let Vector2(x2, y2) = opts.view.location;
+                    let y = position.y() + y2;
+                    (editor.column_at_display(y, position.x() + x2), y)
+                }
             }};
         }
 
         let _empty = KeyModifiers::empty();
 
         match event {
-            MouseEvent::Down(MouseButton::Left, x, y, _empty) => {
+            MouseEvent::Down(MouseButton::Left, x, y, modifiers) => {
                 let (x, y) = (x as i32, y as i32);
+                // clicking on another pane focuses it
+                match self.pane_rects.iter().position(|r| r.contains(Vector2(x, y))) {
+                    Some(index) => self.focus_pane(index),
+                    None => return,
+                }
                 let (x, y) = to_editor_coords!(x, y);
-                self.log = format!("mouse: set cursor location to {}:{}", x, y);
-                self.editor.set_cursor((x, y));
+                if modifiers.contains(KeyModifiers::ALT) {
+                    // alt+click adds a cursor
+                    self.buffers.editor_mut().add_cursor((x, y));
+                } else {
+                    self.log = format!("mouse: set cursor location to {}:{}", x, y);
+                    let clicks = self.count_click(Vector2(x, y));
+                    let editor = self.buffers.editor_mut();
+                    editor.clear_cursors();
+                    editor.clear_selection();
+                    // a double click selects a word and a triple click the whole line
+                    match clicks {
+                        2 => {
+                            let (start, end) = editor.word_at((x, y));
+                            editor.select_range(start, end);
+                        }
+                        3 => {
+                            let (start, end) = editor.line_range(y);
+                            editor.select_range(start, end);
+                        }
+                        _ => {
+                            editor.set_cursor((x, y));
+                        }
+                    }
+                }
                 self.render();
             },
+            MouseEvent::Drag(MouseButton::Left, x, y, modifiers)
+                if modifiers.contains(KeyModifiers::ALT) =>
+            {
+                // alt+drag selects a block from where the button was pressed, which alt+click
+                // left as the primary cursor
+                let (x, y) = (x as i32, y as i32);
+                let (x, y) = to_editor_coords!(x, y);
+                let editor = self.buffers.editor_mut();
+                if editor.block().is_none() {
+                    editor.clear_cursors();
+                }
+                let column = editor.display_column(Vector2(x, y));
+                editor.extend_block((column, y));
+                self.render();
+            }
+            MouseEvent::Drag(MouseButton::Left, x, y, _) => {
+                // dragging selects from where the button was pressed, or extends the word or
+                // line selected by a double or triple click
+                let (x, y) = (x as i32, y as i32);
+                let (x, y) = to_editor_coords!(x, y);
+                let editor = self.buffers.editor_mut();
+                if !editor.is_selecting() {
+                    editor.begin_select();
+                }
+                editor.set_cursor((x, y));
+                self.render();
+            }
+            MouseEvent::Up(MouseButton::Left, _, _, _) => {
+                // a drag back to where it started leaves nothing selected
+                let editor = self.buffers.editor_mut();
+                if editor.block().is_none()
+                    && editor.selection_range().is_some_and(|(start, end)| start == end)
+                {
+                    editor.clear_selection();
+                    self.render();
+                }
+            }
             _ => self.log = "unknown mouse event".to_string(),
         }
     }
 
     pub fn process_key_event(&mut self, event: KeyEvent) {
+        if let Some(prompt) = self.macro_prompt.take() {
+            self.process_macro_prompt(prompt, event);
+            return;
+        }
+        if let Some(prompt) = self.register_prompt.take() {
+            self.process_register_prompt(prompt, event);
+            return;
+        }
+        if self.search.is_some() {
+            self.process_search_key_event(event);
+            return;
+        }
+        if self.replace.is_some() {
+            self.process_replace_key_event(event);
+            return;
+        }
+        if self.command_line.is_some() {
+            self.process_command_line_key_event(event);
+            return;
+        }
+        if let Some(vi) = self.vi.as_mut() {
+            if vi.handle_key(self.buffers.editor_mut(), event) {
+                self.render();
+                return;
+            }
+            // : opens the command line in normal mode, like in vi
+            if vi.mode == Mode::Normal && event.code == KeyCode::Char(':') {
+                self.execute(Command::CommandLine);
+                return;
+            }
+        }
+
+        match self.keymap.get(event) {
+            Some(command) => self.execute(command),
+            // characters without a binding are typed into the buffer
+            None => {
+                if let Some(c) = keymap::typed_char(event) {
+                    self.confirm_close = false;
+                    self.pasted = None;
+                    self.buffers.editor_mut().write(c);
+                    self.render();
+                }
+            }
+        }
+    }
+
+    /// run a command on the focused pane
+    pub fn execute(&mut self, command: Command) {
+        // scroll the view, bringing the cursor along if it would leave the view
         macro_rules! move_view {
             ($x:expr, $y:expr) => {
-                self.render_opts.view.location =
-                    self.render_opts.view.location.add(Vector2($x, $y));
+                let editor = self.buffers.editor();
+                let mut opts = self.buffers.render_opts();
+                if self.wrap {
+                    // wrapped lines don't scroll sideways
+                    wrap::scroll(editor, &mut opts.view, $y);
+                } else {
+                    opts.view.location = opts.view.location.add(Vector2($x, $y));
+                }
+                opts.clamp(editor);
+                let cursor = opts.constrain(editor, editor.cursor_pos(), self.scrolloff);
+                *self.buffers.render_opts_mut() = opts;
+                self.buffers.editor_mut().set_cursor(cursor);
                 self.render();
             };
         }
 
         macro_rules! move_cursor {
             ($x:expr, $y:expr) => {
-                self.editor.move_cursor(($x, $y));
-                self.update_cursor_pos();
+                if self.wrap && $y != 0 {
+                    // move by screen rows rather than lines
+                    let width = self.buffers.render_opts().view.width;
+                    self.buffers.editor_mut().each_cursor(|editor| {
+                        let location = wrap::move_vertically(editor, editor.cursor_pos(), $y, width);
+                        editor.set_cursor(location);
+                    });
+                } else {
+                    self.buffers.editor_mut().move_cursor(($x, $y));
+                }
+                self.render();
             };
         }
 
         macro_rules! set_cursor {
             ($x:expr, $y:expr) => {
-                self.editor.set_cursor(($x, $y));
+                let location = ($x, $y);
+                self.buffers.editor_mut().set_cursor(location);
                 self.render();
             };
             ($x:expr) => {
-                self.editor.set_cursor($x);
+                let location = $x;
+                self.buffers.editor_mut().set_cursor(location);
                 self.render();
             };
         }
 
-        match event.code {
-            KeyCode::Down if event.modifiers.is_empty() => {
+        // closing a modified buffer has to be confirmed by running the command again straight away
+        let confirm_close = std::mem::take(&mut self.confirm_close);
+        // only the command straight after a paste can swap it for an earlier copy
+        let pasted = self.pasted.take();
+
+        match command {
+            Command::MoveDown => {
+                self.buffers.editor_mut().clear_selection();
                 move_cursor!(0, 1);
             },
-            KeyCode::Up if event.modifiers.is_empty() => {
+            Command::MoveUp => {
+                self.buffers.editor_mut().clear_selection();
                 move_cursor!(0, -1);
             },
-            KeyCode::Right if event.modifiers.is_empty() => {
+            Command::MoveRight => {
+                self.buffers.editor_mut().clear_selection();
                 move_cursor!(1, 0);
             },
-            KeyCode::Left if event.modifiers.is_empty() => {
+            Command::MoveLeft => {
+                self.buffers.editor_mut().clear_selection();
                 move_cursor!(-1, 0);
             },
-            KeyCode::Down if event.modifiers.contains(KeyModifiers::CONTROL) => {
+            Command::ScrollDown => {
                 move_view!(0, 1);
             },
-            KeyCode::Up if event.modifiers.contains(KeyModifiers::CONTROL) => {
+            Command::ScrollUp => {
                 move_view!(0, -1);
             },
-            KeyCode::Right if event.modifiers.contains(KeyModifiers::CONTROL) => {
+            Command::ScrollRight => {
                 move_view!(1, 0);
             },
-            KeyCode::Left if event.modifiers.contains(KeyModifiers::CONTROL) => {
+            Command::ScrollLeft => {
                 move_view!(-1, 0);
             },
-            KeyCode::Right if event.modifiers.contains(KeyModifiers::SHIFT) => {
-                if !self.editor.is_selecting() {
-                    self.editor.begin_select();
-                }
+            Command::SelectRight => {
+                self.extend_selection();
                 move_cursor!(1, 0);
             },
-            KeyCode::Left if event.modifiers.contains(KeyModifiers::SHIFT) => {
-                if !self.editor.is_selecting() {
-                    self.editor.begin_select();
-                }
+            Command::SelectLeft => {
+                self.extend_selection();
                 move_cursor!(-1, 0);
             }
-            KeyCode::F(1) => {
+            Command::SelectUp => {
+                self.extend_selection();
+                move_cursor!(0, -1);
+            }
+            Command::SelectDown => {
+                self.extend_selection();
+                move_cursor!(0, 1);
+            }
+            Command::SelectWordLeft | Command::SelectWordRight => {
+                let direction = if command == Command::SelectWordLeft { -1 } else { 1 };
+                self.extend_selection();
+                self.buffers.editor_mut().each_cursor(|editor| {
+                    let location = editor.word_boundary(editor.cursor_pos(), direction);
+                    editor.set_cursor(location);
+                });
+                self.render();
+            }
+            Command::SelectLinesUp => {
+                self.buffers.editor_mut().each_cursor(|editor| {
+                    let Vector2(x, y) = editor.cursor_pos();
+                    if !editor.is_selecting() {
+                        editor.begin_select_at(editor.line_range(y).1);
+                    }
+                    // the start of the line the cursor is on, then the line above
+                    editor.set_cursor((0, if x > 0 { y } else { y - 1 }));
+                });
+                self.render();
+            }
+            Command::SelectLinesDown => {
+                self.buffers.editor_mut().each_cursor(|editor| {
+                    let y = editor.cursor_pos().y();
+                    if !editor.is_selecting() {
+                        editor.begin_select_at((0, y));
+                    }
+                    let (_, end) = editor.line_range(y);
+                    editor.set_cursor(end);
+                });
+                self.render();
+            }
+            Command::FocusLeft => self.move_focus(Vector2(-1, 0)),
+            Command::FocusRight => self.move_focus(Vector2(1, 0)),
+            Command::FocusUp => self.move_focus(Vector2(0, -1)),
+            Command::FocusDown => self.move_focus(Vector2(0, 1)),
+            Command::GrowLeft => self.grow_pane(Vector2(-1, 0)),
+            Command::GrowRight => self.grow_pane(Vector2(1, 0)),
+            Command::GrowUp => self.grow_pane(Vector2(0, -1)),
+            Command::GrowDown => self.grow_pane(Vector2(0, 1)),
+            Command::Help => {
                 std::io::stdout().execute(MoveTo(0, 0)).unwrap();
                 std::io::stdout().execute(Clear(ClearType::All)).unwrap();
-                println!("{}", include_str!("../resources/help_text.txt"));
+                println!("{}", self.keymap.help_text());
+                self.screen.invalidate();
+            }
+            Command::ToggleWrap => {
+                // the view's x means something else with wrapping on, so it starts over
+                self.wrap = !self.wrap;
+                self.buffers.render_opts_mut().view.location.0 = 0;
+                for pane in self.layout.panes_mut() {
+                    pane.render_opts.view.location.0 = 0;
+                }
+                self.log = format!("soft wrap: {}", if self.wrap { "on" } else { "off" });
+                let cursor = self.buffers.editor().cursor_pos();
+                self.reveal(cursor);
+                self.render();
             }
-            KeyCode::F(5) => {
+            Command::ToggleVi => {
+                self.vi = match self.vi.take() {
+                    Some(mut vi) => {
+                        // leave insert or visual mode, so the buffer isn't left mid change
+                        vi.handle_key(self.buffers.editor_mut(), KeyEvent::from(KeyCode::Esc));
+                        None
+                    }
+                    None => Some(Vi::new()),
+                };
+                self.log = format!("vi mode: {}", if self.vi.is_some() { "on" } else { "off" });
                 self.render();
             }
-            KeyCode::Char('c') if event.modifiers.contains(KeyModifiers::CONTROL) => {
-                self.editor.copy();
+            Command::CycleLineNumbers => {
+                // cycle the gutter through no line numbers, absolute and relative numbers
+                self.gutter.numbers = self.gutter.numbers.next();
+                self.log = format!("line numbers: {:?}", self.gutter.numbers);
+                self.render();
             }
-            KeyCode::Char('a') if event.modifiers.contains(KeyModifiers::CONTROL) => {
-                // bring the cursor to the top of the viewport
-                //self.editor.begin_select_at();
+            Command::Refresh => {
+                self.screen.invalidate();
+                self.render();
+            }
+            Command::CycleLineEndings => {
+                // cycle the line endings of the whole file through LF, CRLF and CR
+                let style = self.buffers.editor().line_ending().next();
+                self.buffers.editor_mut().set_line_ending(style);
+                self.log = format!("converted line endings to {}", style);
+                self.render();
+            }
+            Command::Copy | Command::Cut => {
+                let editor = self.buffers.editor_mut();
+                let cells = if command == Command::Cut { editor.cut() } else { editor.copy() };
+                match cells {
+                    Some(cells) => {
+                        let text: String = cells.into_iter().map(|cell| cell.char).collect();
+                        if let Err(e) = self.clipboard.copy(text) {
+                            self.log = e.to_string();
+                        }
+                    }
+                    None => self.log = "nothing selected".to_string(),
+                }
+                self.render();
+            }
+            Command::CursorToMiddle => {
+                // bring the cursor to the middle of the viewport
+                //self.buffers.editor().begin_select_at();
                 set_cursor!((
                     0,
-                    self.render_opts.view.location.y() + (self.render_opts.view.height / 2)
+                    self.buffers.render_opts().view.location.y() + (self.buffers.render_opts().view.height / 2)
                 ));
             }
-            KeyCode::Char('l') if event.modifiers.contains(KeyModifiers::CONTROL) => {
+            Command::CenterView => {
                 // center the screen on the cursor
-                self.render_opts.view.location.1 =
-                    self.editor.cursor_pos().y() - (self.render_opts.view.height / 2);
+                let y = self.buffers.editor().cursor_pos().y() - (self.buffers.render_opts().view.height / 2);
+                self.buffers.render_opts_mut().view.location.1 = y;
+                self.render();
+            },
+            Command::NextBuffer => {
+                self.buffers.next();
+                self.show_active_buffer();
                 self.render();
             },
-            KeyCode::Char('b') if event.modifiers.contains(KeyModifiers::CONTROL) => {
+            Command::PreviousBuffer => {
+                self.buffers.previous();
+                self.show_active_buffer();
+                self.render();
+            },
+            Command::SplitHorizontal => {
+                self.split_pane(Split::Horizontal);
+                self.render();
+            },
+            Command::SplitVertical => {
+                self.split_pane(Split::Vertical);
+                self.render();
+            },
+            Command::ClosePane => {
+                self.close_pane();
+                self.render();
+            },
+            Command::ListBuffers => {
+                // list the buffers in the log, the active one is marked with >
+                let names: Vec<String> = self
+                    .buffers
+                    .iter()
+                    .enumerate()
+                    .map(|(i, b)| {
+                        let marker = if i == self.buffers.active_index() { ">" } else { "" };
+                        let modified = if b.editor.is_modified() { "*" } else { "" };
+                        format!("{}{}:{}{}", marker, i + 1, b.name(), modified)
+                    })
+                    .collect();
+                self.log = format!("buffers: {}", names.join(" "));
+                self.render();
+            },
+            Command::CloseBuffer => {
+                if self.buffers.editor().is_modified() && !confirm_close {
+                    self.log = format!(
+                        "{} has unsaved changes, close it again to discard them",
+                        self.buffers.active().name()
+                    );
+                    self.confirm_close = true;
+                } else {
+                    let index = self.buffers.active_index();
+                    let closed = self.buffers.close();
+                    self.log = format!("closed {}", closed.name());
+
+                    // panes showing the closed buffer switch to the new active one
+                    let active = self.buffers.active_index();
+                    for pane in self.layout.panes_mut() {
+                        if pane.buffer == index {
+                            pane.buffer = active;
+                        } else if pane.buffer > index {
+                            pane.buffer -= 1;
+                        }
+                    }
+                }
+                self.render();
+            },
+            Command::Exit => {
                 self.exit = true;
             },
-            KeyCode::Char('v') if event.modifiers.contains(KeyModifiers::CONTROL) => {
-                self.clipboard.paste().unwrap();
+            Command::Save => {
+                self.save();
+                self.render();
+            },
+            Command::Search => {
+                self.search = Some(IncrementalSearch::new(
+                    self.buffers.editor().cursor_pos(),
+                    self.buffers.render_opts().view.location,
+                ));
+                self.render();
+            },
+            Command::Replace => {
+                // replace within the selection if there is one, otherwise the whole file
+                self.replace = Some(ReplaceSession::new(self.buffers.editor()));
+                self.render();
+            },
+            Command::Undo => {
+                if !self.buffers.editor_mut().undo() {
+                    self.log = "nothing to undo".to_string();
+                }
+                self.render();
+            },
+            Command::Redo => {
+                if !self.buffers.editor_mut().redo() {
+                    self.log = "nothing to redo".to_string();
+                }
+                self.render();
+            },
+            Command::Paste => {
+                match self.clipboard.paste() {
+                    Ok(text) => {
+                        self.buffers.editor_mut().paste(&text);
+                        self.pasted = Some((self.clipboard.position(&text), text));
+                    }
+                    Err(e) => self.log = e.to_string(),
+                }
+                self.render();
             }
-            KeyCode::Char(x) => {
-                self.editor.write(x);
-                self.render_break_line_hint = true;
-                self.render_line_hint = Some(self.editor.cursor_pos().y());
+            Command::PasteOlder => {
+                let (index, text) = match pasted {
+                    Some(pasted) => pasted,
+                    None => {
+                        self.log = "paste first to go back to earlier copies".to_string();
+                        self.render();
+                        return;
+                    }
+                };
+                // go back one copy, wrapping around to the latest after the oldest
+                let older = index.map_or(0, |index| index + 1) % self.clipboard.len().max(1);
+                match self.clipboard.get(older).map(String::from) {
+                    Some(entry) if self.buffers.editor_mut().replace_paste(&text, &entry) => {
+                        self.log = format!("pasted copy {} of {}", older + 1, self.clipboard.len());
+                        self.pasted = Some((Some(older), entry));
+                    }
+                    Some(_) => self.log = "the pasted text has changed".to_string(),
+                    None => self.log = "nothing has been copied".to_string(),
+                }
                 self.render();
             }
-            KeyCode::Backspace => {
-                if let Some(x) = self.editor.delete() {
-                    if x.char != '\n' {
-                        self.render_line_hint = Some(self.editor.cursor_pos().y());
+            Command::CopyToRegister => {
+                self.register_prompt = Some(RegisterPrompt::Copy);
+                self.log = "copy to register:".to_string();
+                self.render();
+            }
+            Command::PasteFromRegister => {
+                self.register_prompt = Some(RegisterPrompt::Paste);
+                self.log = "paste from register:".to_string();
+                self.render();
+            }
+            Command::DeleteBackward => {
+                self.buffers.editor_mut().delete();
+                self.render();
+            }
+            Command::RecordMacro => {
+                self.log = match self.macros.stop() {
+                    Some(name) => format!("recorded macro @{}", name),
+                    None => {
+                        self.macro_prompt = Some(MacroPrompt::Record);
+                        "record macro into register:".to_string()
                     }
+                };
+                self.render();
+            }
+            Command::PlayMacro => {
+                self.macro_prompt = Some(MacroPrompt::Play { count: None });
+                self.log = "play macro in register:".to_string();
+                self.render();
+            }
+            Command::SaveMacros => {
+                self.log = match self.macros.save() {
+                    Ok(path) => format!("saved macros to {}", path.display()),
+                    Err(e) => format!("error saving macros: {}", e),
+                };
+                self.render();
+            }
+            Command::CommandLine => {
+                self.command_line = Some(CommandLine::new());
+                self.render();
+            }
+            Command::Newline => {
+                self.buffers.editor_mut().write('\n');
+                self.render();
+            }
+            Command::LineStart | Command::SelectLineStart => {
+                if command == Command::SelectLineStart {
+                    self.extend_selection();
+                } else {
+                    self.buffers.editor_mut().clear_selection();
+                }
+                self.buffers.editor_mut().each_cursor(|editor| {
+                    editor.set_cursor((0, editor.cursor_pos().y()));
+                });
+                self.render();
+            }
+            Command::LineEnd | Command::SelectLineEnd => {
+                if command == Command::SelectLineEnd {
+                    self.extend_selection();
+                } else {
+                    self.buffers.editor_mut().clear_selection();
+                }
+                self.buffers.editor_mut().each_cursor(|editor| {
+                    editor.set_cursor((editor.line_len() as i32, editor.cursor_pos().y()));
+                });
+                self.render();
+            }
+            Command::BlockLeft => self.extend_block(Vector2(-1, 0)),
+            Command::BlockRight => self.extend_block(Vector2(1, 0)),
+            Command::BlockUp => self.extend_block(Vector2(0, -1)),
+            Command::BlockDown => self.extend_block(Vector2(0, 1)),
+            Command::AddCursorAbove => {
+                self.buffers.editor_mut().add_cursor_vertically(-1);
+                self.render();
+            }
+            Command::AddCursorBelow => {
+                self.buffers.editor_mut().add_cursor_vertically(1);
+                self.render();
+            }
+            Command::AddNextMatch => {
+                if !self.buffers.editor_mut().add_cursor_at_next_match() {
+                    self.log = "select some text with another occurrence first".to_string();
                 }
                 self.render();
             }
+            Command::SingleCursor => {
+                self.buffers.editor_mut().clear_cursors();
+                self.render();
+            }
+        }
+    }
+
+    /// start selecting from the cursor, unless a selection is already being extended
+    fn extend_selection(&mut self) {
+        if !self.buffers.editor().is_selecting() {
+            self.buffers.editor_mut().begin_select();
+        }
+    }
+
+    /// move the moving corner of the block selection, starting one at the cursor if needed
+    fn extend_block(&mut self, direction: Vector2) {
+        let editor = self.buffers.editor_mut();
+        if editor.block().is_none() {
+            editor.begin_block();
+        }
+        if let Some(block) = editor.block() {
+            editor.extend_block(block.head.add(direction));
+        }
+        self.render();
+    }
+
+    /// move the focus to the pane next to the focused one in `direction`
+    fn move_focus(&mut self, direction: Vector2) {
+        if let Some(index) = layout::neighbor(&self.pane_rects, self.focus, direction) {
+            self.focus_pane(index);
+        }
+        self.render();
+    }
+
+    /// move the edge of the focused pane in `direction`, which grows or shrinks it
+    fn grow_pane(&mut self, direction: Vector2) {
+        let split = if direction.x() != 0 { Split::Vertical } else { Split::Horizontal };
+        let amount = (direction.x() + direction.y()) as f32 * RESIZE_STEP;
+        self.layout.resize(self.focus, split, amount);
+        self.render();
+    }
+
+    /// handle a key while the search prompt is open
+    fn process_search_key_event(&mut self, event: KeyEvent) {
+        let search = match self.search.as_mut() {
+            Some(search) => search,
+            None => return,
+        };
+
+        let found = match event.code {
+            KeyCode::Esc => {
+                // return to where the search started
+                let (origin, origin_view) = (search.origin, search.origin_view);
+                self.search = None;
+                self.buffers.editor_mut().set_cursor(origin);
+                self.buffers.render_opts_mut().view.location = origin_view;
+                self.buffers.editor_mut().set_highlights(Vec::new());
+                self.render();
+                return;
+            }
             KeyCode::Enter => {
-                self.editor.write('\n');
+                self.search = None;
+                self.buffers.editor_mut().set_highlights(Vec::new());
                 self.render();
+                return;
             }
-            KeyCode::Home => {
-                set_cursor!(0, self.editor.cursor_pos().y());
+            KeyCode::Char('n') if event.modifiers.contains(KeyModifiers::CONTROL) => {
+                search.step(self.buffers.editor(), Direction::Forward)
             }
-            KeyCode::End => {
-                set_cursor!(self.editor.line_len() as i32, self.editor.cursor_pos().y());
+            KeyCode::Char('p') if event.modifiers.contains(KeyModifiers::CONTROL) => {
+                search.step(self.buffers.editor(), Direction::Backward)
+            }
+            KeyCode::Down => search.step(self.buffers.editor(), Direction::Forward),
+            KeyCode::Up => search.step(self.buffers.editor(), Direction::Backward),
+            KeyCode::Backspace => {
+                search.query.pop();
+                search.update(self.buffers.editor())
+            }
+            _ => match keymap::typed_char(event) {
+                Some(c) => {
+                    search.query.push(c);
+                    search.update(self.buffers.editor())
+                }
+                None => return,
+            },
+        };
+
+        match found {
+            Some(m) => {
+                self.buffers.editor_mut().set_cursor(m.start);
+                self.reveal(m.start);
+            }
+            None => {
+                let origin = search.origin;
+                self.buffers.editor_mut().set_cursor(origin);
             }
-            _ => {}
         }
+        self.update_search_highlights();
+        self.render();
     }
 
-    /// render the screen to crossterm.
-    /// if self.render_line_hint is not None, only that line will be rendered
-    pub fn render(&mut self) {
-        let mut stdout = std::io::stdout();
-        // if self.exit {
-        //     stdout.execute(Clear(ClearType::All)).unwrap();
-        //     ()
-        // }
-        self.update_view_size().unwrap();
+    /// handle the key naming the register of a macro, or a digit of the count to play it
+    fn process_macro_prompt(&mut self, prompt: MacroPrompt, event: KeyEvent) {
+        let c = match keymap::typed_char(event) {
+            Some(c) => c,
+            None => {
+                self.log.clear();
+                self.render();
+                return;
+            }
+        };
 
-        // render a single line if the line hint is not None
-        if let Some(line) = self.render_line_hint {
-            self.render_line(line);
+        match prompt {
+            MacroPrompt::Play { count } if c.is_ascii_digit() => {
+                let count = count.unwrap_or(0) * 10 + c.to_digit(10).unwrap() as usize;
+                self.macro_prompt = Some(MacroPrompt::Play { count: Some(count) });
+                self.log = format!("play macro {} times, in register:", count);
+            }
+            _ if !Macros::is_register(c) => self.log = format!("{} isn't a register, use a letter", c),
+            MacroPrompt::Record => {
+                self.macros.start(c);
+                self.log = format!("recording macro @{}, stop with the same key", c);
+            }
+            MacroPrompt::Play { count } => {
+                self.log.clear();
+                self.play_macro(c, count.unwrap_or(1));
+            }
+        }
+        self.render();
+    }
+
+    /// handle the key naming the register to copy the selection to or paste from
+    fn process_register_prompt(&mut self, prompt: RegisterPrompt, event: KeyEvent) {
+        let name = match keymap::typed_char(event) {
+            Some(c) => c,
+            None => {
+                self.log.clear();
+                self.render();
+                return;
+            }
+        };
+        if !KillRing::<T>::is_register(name) {
+            self.log = format!("{} isn't a register, use a letter", name);
+            self.render();
             return;
         }
 
-        let text = StringRenderer::new().render(&self.editor, self.render_opts);
+        match prompt {
+            RegisterPrompt::Copy => match self.buffers.editor().copy() {
+                Some(cells) => {
+                    let text: String = cells.into_iter().map(|cell| cell.char).collect();
+                    self.clipboard.set_register(name, text);
+                    self.log = format!("copied to register {}", name);
+                }
+                None => self.log = "nothing selected".to_string(),
+            },
+            RegisterPrompt::Paste => match self.clipboard.register(name).map(String::from) {
+                Some(text) => {
+                    self.buffers.editor_mut().paste(&text);
+                    // going back from a register starts at the latest copy
+                    self.pasted = Some((None, text));
+                    self.log.clear();
+                }
+                None => self.log = format!("register {} is empty", name),
+            },
+        }
+        self.render();
+    }
 
-        // stdout
-        //     .execute(MoveTo(0,0)).unwrap()
-        //     .execute(style::Print(self.editor.get_cell(Vector2(0, 0)).unwrap()));
-        stdout.execute(MoveTo(0, 0)).unwrap();
-        write!(
-            &mut stdout,
-            "{}[F1 to display help ] {:?} Selection:{}",
-            text, self.render_opts, self.editor.selection()
-        )
-        .unwrap();
+    /// play the macro in register `name` `count` times.
+    /// the edits it makes are undone together. nothing is drawn while it plays, the caller
+    /// draws the screen once it is done
+    pub fn play_macro(&mut self, name: char, count: usize) {
+        let keys = match self.macros.get(name) {
+            Some(keys) => keys.to_vec(),
+            None => {
+                self.log = format!("no macro in register {}", name);
+                return;
+            }
+        };
+        // a macro which plays itself would never finish
+        if self.playing.contains(&name) {
+            self.log = format!("macro @{} can't play itself", name);
+            return;
+        }
 
-        self.update_cursor_pos();
+        self.playing.push(name);
+        for buffer in self.buffers.iter_mut() {
+            buffer.editor.begin_group();
+        }
+        'play: for _ in 0..count {
+            for key in &keys {
+                if self.exit {
+                    break 'play;
+                }
+                self.process_key_event(*key);
+            }
+        }
+        // buffers opened while playing end a group they never began, which does nothing
+        for buffer in self.buffers.iter_mut() {
+            buffer.editor.end_group();
+        }
+        self.playing.pop();
     }
 
-    pub fn clear_render_hints(&mut self) {
-        self.render_break_line_hint = false;
-        self.render_line_hint = None;
+    /// handle a key while a command is being typed
+    fn process_command_line_key_event(&mut self, event: KeyEvent) {
+        let line = match self.command_line.as_mut() {
+            Some(line) => line,
+            None => return,
+        };
+
+        match event.code {
+            KeyCode::Esc => self.command_line = None,
+            KeyCode::Enter => {
+                let input = line.input.trim().to_string();
+                self.command_line = None;
+                if !input.is_empty() {
+                    if self.command_history.last() != Some(&input) {
+                        self.command_history.push(input.clone());
+                    }
+                    self.run_command_line(&input);
+                }
+            }
+            // backspace on an empty line closes it, like in vi
+            KeyCode::Backspace if line.input.is_empty() => self.command_line = None,
+            KeyCode::Backspace => line.pop(),
+            KeyCode::Up => line.previous(&self.command_history),
+            KeyCode::Down => line.next(&self.command_history),
+            KeyCode::Tab => line.complete(),
+            _ => match keymap::typed_char(event) {
+                Some(c) => line.push(c),
+                None => return,
+            },
+        }
+        self.render();
     }
 
-    pub fn update_cursor_pos(&self) {
-        if self.render_opts.view.contains(self.editor.cursor_pos()) {
-            // place the cursor over the current character
-            let x = self.render_opts.view.x();
-            let y = self.render_opts.view.y();
+    /// run a line typed on the command line, reporting errors in the log
+    pub fn run_command_line(&mut self, input: &str) {
+        let typed = match command_line::parse(input) {
+            Ok(typed) => typed,
+            Err(e) => {
+                self.log = e;
+                return;
+            }
+        };
 
-            // obtain the position of the cursor relative to the screen
-            let real_x = self.editor.cursor_pos().x() - x;
-            let real_y = self.editor.cursor_pos().y() - y;
+        match typed {
+            Typed::Write(None) => self.execute(Command::Save),
+            Typed::Write(Some(path)) => {
+                self.log = match self.buffers.editor_mut().save_as(&path) {
+                    Ok(()) => format!("saved {}", path),
+                    Err(e) => format!("error saving {}: {}", path, e),
+                };
+            }
+            Typed::Quit { force } => {
+                let unsaved = self.buffers.iter().find(|b| b.editor.is_modified());
+                match unsaved {
+                    Some(buffer) if !force => {
+                        self.log = format!(
+                            "{} has unsaved changes, use :q! to quit anyway",
+                            buffer.name()
+                        );
+                    }
+                    _ => self.execute(Command::Exit),
+                }
+            }
+            Typed::WriteQuit => {
+                self.save();
+                if !self.buffers.editor().is_modified() {
+                    self.execute(Command::Exit);
+                }
+            }
+            Typed::Edit(path) => match Editor::open(&path) {
+                Ok(editor) => {
+                    self.open(editor);
+                    self.log = format!("opened {}", path);
+                }
+                Err(e) => self.log = format!("error opening {}: {}", path, e),
+            },
+            Typed::Goto(line) => {
+                let cursor = self.buffers.editor_mut().set_cursor((0, line as i32 - 1));
+                self.reveal(cursor);
+            }
+            Typed::Set(setting) => self.set(setting),
+            Typed::Run(command) => self.execute(command),
+        }
+    }
 
-            std::io::stdout()
-                .execute(MoveTo(real_x as u16, real_y as u16))
-                .unwrap();
+    /// change a setting, from the config file or the command line
+    pub fn set(&mut self, setting: Setting) {
+        match setting {
+            Setting::Vi(on) if on != self.vi.is_some() => self.execute(Command::ToggleVi),
+            Setting::Wrap(on) if on != self.wrap => self.execute(Command::ToggleWrap),
+            Setting::Vi(_) | Setting::Wrap(_) => {}
+            Setting::Numbers(numbers) => self.gutter.numbers = numbers,
+            Setting::TabWidth(width) => {
+                self.tab_width = width;
+                for buffer in self.buffers.iter_mut() {
+                    buffer.editor.set_tab_width(width);
+                }
+            }
+            Setting::ScrollOff(rows) => self.scrolloff.rows = rows,
+            Setting::SideScrollOff(columns) => self.scrolloff.columns = columns,
         }
     }
 
-    /// render only a single line of the editor
-    pub fn render_line(&mut self, line: i32) {
-        let ycp = line;
-        let y = ycp - self.render_opts.view.location.y();
-        if self.render_opts.view.contains(Vector2(0, ycp)) {
-            std::io::stdout().execute(MoveTo(0, y as u16)).unwrap();
-            let text = StringRenderer {
-                line_hint: Some(line),
-                break_on_line_end: self.render_break_line_hint,
+    /// handle a key while a find and replace is in progress
+    fn process_replace_key_event(&mut self, event: KeyEvent) {
+        let session = match self.replace.as_mut() {
+            Some(session) => session,
+            None => return,
+        };
+        let typed = keymap::typed_char(event).is_some();
+
+        match (session.step, event.code) {
+            (_, KeyCode::Esc) | (ReplaceStep::Confirm, KeyCode::Char('q')) => {
+                self.finish_replace();
+                return;
+            },
+            (ReplaceStep::Pattern, KeyCode::Enter) => session.step = ReplaceStep::Template,
+            (ReplaceStep::Pattern, KeyCode::Backspace) => {
+                session.pattern.pop();
+            },
+            (ReplaceStep::Pattern, KeyCode::Char(c)) if typed => session.pattern.push(c),
+            (ReplaceStep::Template, KeyCode::Backspace) => {
+                session.template.pop();
+            },
+            (ReplaceStep::Template, KeyCode::Char(c)) if typed => session.template.push(c),
+            (ReplaceStep::Template, KeyCode::Enter) | (ReplaceStep::Template, KeyCode::Char('r')) => {
+                if let Err(e) = session.compile() {
+                    self.log = format!("invalid pattern: {}", e);
+                    self.replace = None;
+                    self.render();
+                    return;
+                }
+
+                if event.code == KeyCode::Enter {
+                    // replace everything at once
+                    session.replace_all(self.buffers.editor_mut());
+                    self.finish_replace();
+                    return;
+                }
+
+                // ask before replacing each match
+                session.step = ReplaceStep::Confirm;
+                session.find_from(self.buffers.editor(), Vector2(0, 0));
+            },
+            (ReplaceStep::Confirm, KeyCode::Char('y')) => {
+                session.accept(self.buffers.editor_mut());
+            },
+            (ReplaceStep::Confirm, KeyCode::Char('n')) => {
+                session.skip(self.buffers.editor());
+            },
+            (ReplaceStep::Confirm, KeyCode::Char('a')) => {
+                session.replace_all(self.buffers.editor_mut());
+            },
+            _ => return,
+        }
+
+        if session.step == ReplaceStep::Confirm {
+            match session.current.clone() {
+                Some(current) => {
+                    self.buffers.editor_mut().set_cursor(current.start);
+                    self.buffers.editor_mut().set_highlights(vec![Highlight {
+                        start: current.start,
+                        end: current.end,
+                        bg: CURRENT_MATCH_COLOR,
+                    }]);
+                    self.reveal(current.start);
+                },
+                None => {
+                    self.finish_replace();
+                    return;
+                },
+            }
+        }
+        self.render();
+    }
+
+    /// close the replace prompt and report how many replacements were made
+    fn finish_replace(&mut self) {
+        if let Some(session) = self.replace.take() {
+            self.log = format!("replaced {} occurrence(s)", session.count);
+            if session.range.is_some() {
+                self.buffers.editor_mut().clear_selection();
+            }
+        }
+        self.buffers.editor_mut().set_highlights(Vec::new());
+        self.render();
+    }
+
+    /// highlight the matches of the current search that are in view
+    fn update_search_highlights(&mut self) {
+        let search = match self.search.as_ref() {
+            Some(search) => search,
+            None => return,
+        };
+
+        let view = self.buffers.render_opts().view;
+        let query = Query::from(search.query.as_str());
+        let mut highlights: Vec<Highlight> = search
+            .current
+            .iter()
+            .map(|m| Highlight {
+                start: m.start,
+                end: m.end,
+                bg: CURRENT_MATCH_COLOR,
+            })
+            .collect();
+        highlights.extend(
+            query
+                .find_all(self.buffers.editor(), view.y()..view.y() + view.height)
+                .into_iter()
+                .map(|m| Highlight {
+                    start: m.start,
+                    end: m.end,
+                    bg: MATCH_COLOR,
+                }),
+        );
+        self.buffers.editor_mut().set_highlights(highlights);
+    }
+
+    /// scroll the view so that `location` is visible, centering it if it was off screen
+    pub fn reveal(&mut self, location: Vector2) {
+        self.update_view_size().unwrap();
+        if self.wrap {
+            let mut view = self.buffers.render_opts().view;
+            wrap::reveal(self.buffers.editor(), &mut view, location);
+            self.buffers.render_opts_mut().view = view;
+            return;
+        }
+        let column = self.buffers.editor().display_column(location);
+        let view = &mut self.buffers.render_opts_mut().view;
+
+        if location.y() < view.y() || location.y() >= view.y() + view.height {
+            view.location.1 = (location.y() - view.height / 2).max(0);
+        }
+        if column < view.x() || column >= view.x() + view.width {
+            view.location.0 = (column - view.width / 2).max(0);
+        }
+    }
+
+    /// scroll the focused pane to keep the cursor away from its edges
+    fn follow_cursor(&mut self) {
+        let editor = self.buffers.editor();
+        let mut opts = self.buffers.render_opts();
+        opts.follow(editor, editor.cursor_pos(), self.scrolloff);
+        *self.buffers.render_opts_mut() = opts;
+    }
+
+    /// render the screen to crossterm.
+    /// the frame is drawn off screen and only the cells which changed since the last frame
+    /// are written to the terminal
+    pub fn render(&mut self) {
+        if !self.playing.is_empty() {
+            return;
+        }
+        self.update_view_size().unwrap();
+        self.follow_cursor();
+        self.screen.clear();
+
+        self.render_tab_line();
+        let (rects, separators) = self.layout.arrange(self.editor_area());
+        for (i, (pane, rect)) in self.layout.panes().into_iter().zip(&rects).enumerate() {
+            // the focused pane's view is kept in the active buffer while it has the focus
+            let (editor, opts) = match self.buffers.get(pane.buffer) {
+                Some(_) if i == self.focus => (self.buffers.editor(), self.buffers.render_opts()),
+                Some(buffer) => (&buffer.editor, pane.render_opts),
+                None => continue,
+            };
+            let commands = StyledRenderer::new().render(editor, opts);
+            self.screen.apply(&commands, (rect.x() as u16, rect.y() as u16));
+        }
+        for (rect, split) in separators {
+            let line = match split {
+                Split::Vertical => "│",
+                Split::Horizontal => "─",
+            };
+            for y in rect.y()..rect.y() + rect.height {
+                for x in rect.x()..rect.x() + rect.width {
+                    self.screen.print(x as u16, y as u16, line, Color::DarkGrey, Color::Reset);
+                }
             }
-            .render(&self.editor, self.render_opts);
-            print!("{}", text);
-            self.update_cursor_pos();
-            self.clear_render_hints();
+        }
+
+        // the status line goes below the panes
+        let status = self.status_line();
+        let row = self.screen.height().saturating_sub(1);
+        self.screen.print(0, row, &status, Color::Reset, STATUS_LINE_COLOR);
+
+        let mut commands = self.screen.present();
+        commands.extend(self.cursor_command());
+        renderer::draw(&commands, &mut std::io::stdout()).unwrap();
+    }
+
+    /// draw the name of each buffer along the top of the screen.
+    /// the active buffer is highlighted and modified buffers are marked with a *
+    fn render_tab_line(&mut self) {
+        let mut x = 0;
+        for (i, buffer) in self.buffers.iter().enumerate() {
+            let modified = if buffer.editor.is_modified() { "*" } else { "" };
+            let tab = format!(" {}{} ", buffer.name(), modified);
+            let bg = if i == self.buffers.active_index() { ACTIVE_TAB_COLOR } else { Color::Reset };
+            x = self.screen.print(x, 0, &tab, Color::Reset, bg);
+            x = self.screen.print(x, 0, "|", Color::DarkGrey, Color::Reset);
+        }
+    }
+
+    /// return the text of the status line
+    fn status_line(&self) -> String {
+        if let Some(line) = &self.command_line {
+            return format!(":{}", line.input);
+        }
+        match (&self.replace, &self.search) {
+            (Some(session), _) => {
+                let scope = if session.range.is_some() { " in selection" } else { "" };
+                match session.step {
+                    ReplaceStep::Pattern => format!("replace{}: {}", scope, session.pattern),
+                    ReplaceStep::Template => format!(
+                        "[enter: all, ^R: confirm each] replace {}{} with: {}",
+                        session.pattern, scope, session.template
+                    ),
+                    ReplaceStep::Confirm => format!(
+                        "replace with \"{}\"? (y)es (n)o (a)ll (q)uit",
+                        session.template
+                    ),
+                }
+            },
+            (None, Some(search)) => format!(
+                "search: {}{}",
+                search.query,
+                if search.current.is_none() && !search.query.is_empty() {
+                    " (no match)"
+                } else {
+                    ""
+                }
+            ),
+            (None, None) => {
+                let mut status = StatusLine::new(self.buffers.active(), &self.log);
+                status.mode = self.vi.as_ref().map(|vi| vi.mode.name());
+                status.recording = self.macros.recording();
+                status.format(self.screen.width() as usize)
+            }
+        }
+    }
+
+    /// save the editor to its file, reporting the outcome in the log
+    pub fn save(&mut self) {
+        let name = match self.buffers.editor().path() {
+            Some(path) => path.display().to_string(),
+            None => {
+                self.log = "save: no file name".to_string();
+                return;
+            }
+        };
+
+        self.log = match self.buffers.editor_mut().save() {
+            Ok(()) => format!("saved {}", name),
+            Err(e) => format!("error saving {}: {}", name, e),
+        };
+    }
+
+    /// return the command placing the terminal cursor over the editor cursor, if it is in view
+    fn cursor_command(&self) -> Option<Draw> {
+        // the cursor is shown at the end of the command being typed
+        if let Some(line) = &self.command_line {
+            let end = line.input.chars().count();
+            let x = unicode::display_col(&line.input, end, self.tab_width) + 1;
+            let y = self.screen.height().saturating_sub(1);
+            return Some(Draw::MoveTo(x as u16, y));
+        }
+        // the view is measured in screen columns, which differ from buffer columns
+        // when the line contains wide characters
+        let cursor = self.buffers.editor().cursor_pos();
+        let screen_pos = Vector2(self.buffers.editor().display_column(cursor), cursor.y());
+
+        let origin = self.pane_rects.get(self.focus)?.location;
+        let gutter = self.buffers.render_opts().gutter_width(self.buffers.editor());
+        if self.wrap {
+            let opts = self.buffers.render_opts();
+            let Vector2(x, y) = wrap::locate(self.buffers.editor(), opts.view, cursor)?;
+            return Some(Draw::MoveTo((origin.x() + gutter + x) as u16, (origin.y() + y) as u16));
+        }
+        if self.buffers.render_opts().view.contains(screen_pos) {
+            // obtain the position of the cursor relative to the focused pane, after the gutter
+            let real_x = gutter + screen_pos.x() - self.buffers.render_opts().view.x();
+            let real_y = screen_pos.y() - self.buffers.render_opts().view.y();
+            Some(Draw::MoveTo((origin.x() + real_x) as u16, (origin.y() + real_y) as u16))
         } else {
-            self.clear_render_hints();
-            // the line is not in view, render normally
-            self.render();
+            None
         }
+    }
 
+    /// return the part of the screen the panes are laid out in,
+    /// between the tab line and the status line
+    fn editor_area(&self) -> Rect {
+        Rect {
+            location: Vector2(0, TAB_LINE_HEIGHT as i32),
+            width: self.screen.width() as i32,
+            height: self.screen.height() as i32 - TAB_LINE_HEIGHT as i32 - 1,
+        }
     }
 
     /// update the view size for the renderer
     pub fn update_view_size(&mut self) -> crossterm::Result<()> {
         let (cols, rows) = terminal::size()?;
-        self.render_opts.view.width = cols as i32;
-        self.render_opts.view.height = rows as i32 - 1;
+        self.screen.resize(cols, rows);
+
+        // every pane's view takes the size of its area, less the gutter
+        let (rects, _) = self.layout.arrange(self.editor_area());
+        for (pane, rect) in self.layout.panes_mut().into_iter().zip(&rects) {
+            let opts = &mut pane.render_opts;
+            opts.gutter = self.gutter;
+            opts.wrap = self.wrap;
+            let gutter = self.buffers.get(pane.buffer).map_or(0, |b| opts.gutter_width(&b.editor));
+            opts.view.width = (rect.width - gutter).max(0);
+            opts.view.height = rect.height;
+        }
+        if let Some(rect) = rects.get(self.focus) {
+            self.buffers.render_opts_mut().gutter = self.gutter;
+            self.buffers.render_opts_mut().wrap = self.wrap;
+            let opts = self.buffers.render_opts();
+            let gutter = opts.gutter_width(self.buffers.editor());
+            let view = &mut self.buffers.render_opts_mut().view;
+            view.width = (rect.width - gutter).max(0);
+            view.height = rect.height;
+        }
+        self.pane_rects = rects;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::clipboard::MemoryClipboard;
+
+    fn play(app: &mut Application<MemoryClipboard>, keys: &str, count: usize) {
+        let keys = keys
+            .chars()
+            .map(|c| match c {
+                '\u{1b}' => KeyEvent::from(KeyCode::Esc),
+                '\n' => KeyEvent::from(KeyCode::Down),
+                '\t' => KeyEvent::from(KeyCode::Tab),
+                '<' => KeyEvent::from(KeyCode::Home),
+                '>' => KeyEvent::from(KeyCode::End),
+                c => KeyEvent::from(KeyCode::Char(c)),
+            })
+            .collect();
+        app.macros.set('a', keys);
+        app.play_macro('a', count);
+    }
+
+    #[test]
+    fn test_play_macro() {
+        let text = "one\ntwo\nthree\nfour";
+        let mut app = Application::new(Editor::from(text), MemoryClipboard::new());
+        play(&mut app, "<->!\n", 3);
+        assert_eq!(app.buffers.editor().to_string(), "-one!\n-two!\n-three!\nfour");
+        // every edit of every repetition is undone in one step
+        assert!(app.buffers.editor_mut().undo());
+        assert_eq!(app.buffers.editor().to_string(), text);
+
+        // leaving insert mode ends the vi edit's own group, which stays inside the macro's
+        app.vi = Some(Vi::new());
+        play(&mut app, "Ix\u{1b}Ay\u{1b}j", 2);
+        assert_eq!(app.buffers.editor().to_string(), "xoney\nxtwoy\nthree\nfour");
+        assert!(app.buffers.editor_mut().undo());
+        assert_eq!(app.buffers.editor().to_string(), text);
+    }
+
+    #[test]
+    fn test_tab_key() {
+        // the tab key types a tab, with and without vi
+        let mut app = Application::new(Editor::from("a\nb"), MemoryClipboard::new());
+        play(&mut app, "\t", 1);
+        assert_eq!(app.buffers.editor().to_string(), "\ta\nb");
+        app.vi = Some(Vi::new());
+        play(&mut app, "ji\t\u{1b}", 1);
+        assert_eq!(app.buffers.editor().to_string(), "\ta\n\tb");
+    }
+}
@@ -0,0 +1,175 @@
+//! open buffers. each buffer is an editor along with the part of it in view,
+//! one of them is active and receives input.
+
+use crate::editor::Editor;
+use crate::renderer::RenderOpts;
+
+/// An open file or scratch text
+pub struct Buffer {
+    pub editor: Editor,
+    pub render_opts: RenderOpts,
+}
+
+impl Buffer {
+    pub fn new(editor: Editor) -> Self {
+        Self {
+            editor,
+            render_opts: RenderOpts::default(),
+        }
+    }
+
+    /// return the name shown for the buffer, the file name if it has one
+    pub fn name(&self) -> String {
+        self.editor
+            .path()
+            .and_then(|p| p.file_name())
+            .map_or_else(|| "[untitled]".to_string(), |n| n.to_string_lossy().into_owned())
+    }
+}
+
+/// The open buffers. There is always at least one
+pub struct BufferList {
+    buffers: Vec<Buffer>,
+    active: usize,
+}
+
+impl BufferList {
+    pub fn new(editor: Editor) -> Self {
+        Self {
+            buffers: vec![Buffer::new(editor)],
+            active: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffers.len()
+    }
+
+    /// always false, the list is never empty
+    pub fn is_empty(&self) -> bool {
+        self.buffers.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Buffer> {
+        self.buffers.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Buffer> {
+        self.buffers.iter_mut()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Buffer> {
+        self.buffers.get(index)
+    }
+
+    /// return the index of the active buffer
+    pub fn active_index(&self) -> usize {
+        self.active
+    }
+
+    pub fn active(&self) -> &Buffer {
+        &self.buffers[self.active]
+    }
+
+    pub fn active_mut(&mut self) -> &mut Buffer {
+        &mut self.buffers[self.active]
+    }
+
+    /// return the editor of the active buffer
+    pub fn editor(&self) -> &Editor {
+        &self.active().editor
+    }
+
+    pub fn editor_mut(&mut self) -> &mut Editor {
+        &mut self.active_mut().editor
+    }
+
+    /// return the render options of the active buffer
+    pub fn render_opts(&self) -> RenderOpts {
+        self.active().render_opts
+    }
+
+    pub fn render_opts_mut(&mut self) -> &mut RenderOpts {
+        &mut self.active_mut().render_opts
+    }
+
+    /// add a buffer and make it active.
+    /// if the editor's file is already open, that buffer is made active instead
+    ///
+    /// # Returns
+    /// the index of the active buffer
+    pub fn open(&mut self, editor: Editor) -> usize {
+        let existing = editor
+            .path()
+            .and_then(|path| self.buffers.iter().position(|b| b.editor.path() == Some(path)));
+        self.active = match existing {
+            Some(index) => index,
+            None => {
+                self.buffers.push(Buffer::new(editor));
+                self.buffers.len() - 1
+            }
+        };
+        self.active
+    }
+
+    /// make the buffer at `index` active
+    pub fn select(&mut self, index: usize) {
+        if index < self.buffers.len() {
+            self.active = index;
+        }
+    }
+
+    /// make the next buffer active, wrapping around after the last
+    pub fn next(&mut self) {
+        self.active = (self.active + 1) % self.buffers.len();
+    }
+
+    /// make the previous buffer active, wrapping around before the first
+    pub fn previous(&mut self) {
+        self.active = (self.active + self.buffers.len() - 1) % self.buffers.len();
+    }
+
+    /// remove the active buffer and return it. the buffer before it becomes active,
+    /// and an empty buffer is opened if it was the last one
+    pub fn close(&mut self) -> Buffer {
+        let closed = self.buffers.remove(self.active);
+        if self.buffers.is_empty() {
+            self.buffers.push(Buffer::new(Editor::new()));
+        }
+        self.active = self.active.saturating_sub(1);
+        closed
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_buffer_list() {
+        let mut buffers = BufferList::new(Editor::from("one"));
+        assert_eq!(buffers.open(Editor::from("two")), 1);
+        assert_eq!(buffers.open(Editor::from("three")), 2);
+        assert_eq!(buffers.editor().to_string(), "three");
+
+        buffers.next();
+        assert_eq!(buffers.editor().to_string(), "one");
+        buffers.previous();
+        buffers.previous();
+        assert_eq!(buffers.editor().to_string(), "two");
+
+        // each buffer keeps its own view
+        buffers.render_opts_mut().view.location.1 = 5;
+        buffers.next();
+        assert_eq!(buffers.render_opts().view.y(), 0);
+        buffers.previous();
+        assert_eq!(buffers.render_opts().view.y(), 5);
+
+        assert_eq!(buffers.close().editor.to_string(), "two");
+        assert_eq!(buffers.editor().to_string(), "one");
+        buffers.close();
+        buffers.close();
+        assert_eq!(buffers.len(), 1);
+        assert_eq!(buffers.active().name(), "[untitled]");
+    }
+}
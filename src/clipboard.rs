@@ -2,6 +2,7 @@
 //! temporary text buffer
 use clipboard::{ClipboardContext, ClipboardProvider};
 use std::cell::RefCell;
+use std::collections::{BTreeMap, VecDeque};
 use std::fmt;
 
 #[derive(Debug)]
@@ -55,6 +56,12 @@ pub struct MemoryClipboard {
     pub inner: String,
 }
 
+impl Default for MemoryClipboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl MemoryClipboard {
     pub fn new() -> Self {
         let inner = String::from("");
@@ -64,7 +71,7 @@ impl MemoryClipboard {
 
 impl Clipboard for MemoryClipboard {
     fn paste(&self) -> Result<String> {
-        return Ok(self.inner.clone());
+        Ok(self.inner.clone())
     }
 
     fn copy<T>(&mut self, content: T) -> Result<()>
@@ -113,12 +120,104 @@ impl Clipboard for OsClipboard {
     }
 }
 
+/// the number of copies a `KillRing` keeps unless it is given another capacity
+pub const RING_CAPACITY: usize = 32;
+
+/// What the next key names the register for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegisterPrompt {
+    Copy,
+    Paste,
+}
+
+/// A clipboard which keeps the last few copies, newest first, so earlier ones can be pasted
+/// again, and named registers holding text apart from them.
+/// the latest copy is passed on to the clipboard it wraps, which can be any other clipboard
+pub struct KillRing<C> {
+    pub inner: C,
+    ring: VecDeque<String>,
+    capacity: usize,
+    registers: BTreeMap<char, String>,
+}
+
+impl<C: Clipboard> KillRing<C> {
+    pub fn new(inner: C) -> Self {
+        Self::with_capacity(inner, RING_CAPACITY)
+    }
+
+    /// keep at most `capacity` copies, dropping the oldest
+    pub fn with_capacity(inner: C, capacity: usize) -> Self {
+        Self {
+            inner,
+            ring: VecDeque::new(),
+            capacity: capacity.max(1),
+            registers: BTreeMap::new(),
+        }
+    }
+
+    /// return true if `name` can name a register
+    pub fn is_register(name: char) -> bool {
+        name.is_ascii_alphabetic()
+    }
+
+    /// return the copy made `index` copies ago, the latest is 0
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.ring.get(index).map(String::as_str)
+    }
+
+    /// return how many copies ago `text` was copied
+    pub fn position(&self, text: &str) -> Option<usize> {
+        self.ring.iter().position(|entry| entry == text)
+    }
+
+    pub fn len(&self) -> usize {
+        self.ring.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ring.is_empty()
+    }
+
+    pub fn register(&self, name: char) -> Option<&str> {
+        self.registers.get(&name).map(String::as_str)
+    }
+
+    /// replace the text in the register `name`, which doesn't touch the copies
+    pub fn set_register(&mut self, name: char, text: impl Into<String>) {
+        self.registers.insert(name, text.into());
+    }
+}
+
+impl<C: Clipboard> Clipboard for KillRing<C> {
+    /// Return the contents of the wrapped clipboard, which may have been copied to from
+    /// elsewhere
+    fn paste(&self) -> Result<String> {
+        self.inner.paste()
+    }
+
+    fn copy<T>(&mut self, content: T) -> Result<()>
+    where
+        T: Into<String>,
+    {
+        let content = content.into();
+        if !content.is_empty() {
+            // copying the same text again moves it to the front rather than keeping it twice
+            if let Some(index) = self.position(&content) {
+                self.ring.remove(index);
+            }
+            self.ring.push_front(content.clone());
+            self.ring.truncate(self.capacity);
+        }
+        self.inner.copy(content)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
     //  const TEST_DATA: &'static str = "hello world こんにちは世界";
-    const TEST_DATA: &'static str = include_str!("../resources/sample_text.txt");
+    const TEST_DATA: &str = include_str!("../resources/sample_text.txt");
 
     #[test]
     fn test_memory_clipboard() {
@@ -127,6 +226,29 @@ mod test {
         assert_eq!(TEST_DATA, clipboard.paste().unwrap());
     }
 
+    #[test]
+    fn test_kill_ring() {
+        let mut clipboard = KillRing::with_capacity(MemoryClipboard::new(), 3);
+        for text in &["one", "two", "three", "two", "four"] {
+            clipboard.copy(*text).unwrap();
+        }
+        assert_eq!(clipboard.paste().unwrap(), "four");
+        assert_eq!(clipboard.inner.inner, "four");
+        assert_eq!(clipboard.len(), 3);
+        assert_eq!(
+            (0..4).map(|i| clipboard.get(i)).collect::<Vec<_>>(),
+            vec![Some("four"), Some("two"), Some("three"), None]
+        );
+        assert_eq!(clipboard.position("three"), Some(2));
+
+        clipboard.set_register('a', "kept");
+        clipboard.clear().unwrap();
+        assert_eq!(clipboard.register('a'), Some("kept"));
+        assert_eq!(clipboard.register('b'), None);
+        assert_eq!(clipboard.get(0), Some("four"));
+        assert!(KillRing::<MemoryClipboard>::is_register('Q'));
+    }
+
     #[test]
     fn test_os_clipboard() -> std::result::Result<(), Box<dyn std::error::Error>> {
         let mut clipboard = OsClipboard::new()?;
@@ -0,0 +1,276 @@
+//! the command line, a prompt in place of the status line for typing commands such as
+//! `w`, `e src/main.rs`, `goto 120` or `set tabwidth=4`. the name of any command keys can be
+//! bound to also runs it, so `split-vertical` does the same as alt+v.
+
+use crate::config::Setting;
+use crate::keymap::Command;
+
+/// A command read from the command line
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Typed {
+    /// save the buffer, under a new name if one is given
+    Write(Option<String>),
+    /// exit, which has to be forced when there are unsaved changes
+    Quit { force: bool },
+    WriteQuit,
+    /// open a file in a new buffer
+    Edit(String),
+    /// move the cursor to a line, counting from 1
+    Goto(usize),
+    Set(Setting),
+    /// run one of the commands in the keymap
+    Run(Command),
+}
+
+/// the commands only available on the command line, offered when completing
+const TYPED_NAMES: &[&str] = &["edit", "goto", "quit", "set", "wq", "write"];
+
+/// read a line typed on the command line
+pub fn parse(input: &str) -> Result<Typed, String> {
+    let input = input.trim();
+    let (name, arg) = match input.find(char::is_whitespace) {
+        Some(i) => (&input[..i], input[i..].trim()),
+        None => (input, ""),
+    };
+
+    // a line number on its own goes to that line
+    match name.parse() {
+        Ok(line) if line > 0 && arg.is_empty() => return Ok(Typed::Goto(line)),
+        _ => {}
+    }
+
+    match (name, arg) {
+        ("w" | "write", "") => Ok(Typed::Write(None)),
+        ("w" | "write", path) => Ok(Typed::Write(Some(path.to_string()))),
+        ("q" | "quit", "") => Ok(Typed::Quit { force: false }),
+        ("q!" | "quit!", "") => Ok(Typed::Quit { force: true }),
+        ("wq" | "x", "") => Ok(Typed::WriteQuit),
+        ("e" | "edit", "") => Err("edit needs a file name".to_string()),
+        ("e" | "edit", path) => Ok(Typed::Edit(path.to_string())),
+        ("goto", line) => match line.parse() {
+            Ok(line) if line > 0 => Ok(Typed::Goto(line)),
+            _ => Err("goto needs a line number".to_string()),
+        },
+        ("set", arg) => {
+            // settings are given as name=value, or like in the config file as name value
+            let (setting, value) = arg
+                .split_once('=')
+                .or_else(|| arg.split_once(' '))
+                .ok_or_else(|| "set needs a setting and a value, like tabwidth=4".to_string())?;
+            Setting::parse(setting.trim(), value.trim()).map(Typed::Set)
+        }
+        (name, "") => Command::from_name(name)
+            .map(Typed::Run)
+            .ok_or_else(|| format!("unknown command {}", name)),
+        _ => Err(format!("can't read \"{}\"", input)),
+    }
+}
+
+/// return every way to finish the last word of `input`, each as the whole line it completes to.
+/// the first word is completed from the command names, and the argument from file names or
+/// setting names depending on the command
+pub fn completions(input: &str) -> Vec<String> {
+    let (name, arg) = match input.find(' ') {
+        Some(i) => (&input[..i], input[i + 1..].trim_start()),
+        None => {
+            let mut names: Vec<&str> = TYPED_NAMES
+                .iter()
+                .copied()
+                .chain(Command::names())
+                .filter(|name| name.starts_with(input))
+                .collect();
+            names.sort_unstable();
+            names.dedup();
+            return names.into_iter().map(String::from).collect();
+        }
+    };
+
+    let candidates = match name {
+        "e" | "edit" | "w" | "write" => complete_path(arg),
+        "set" => Setting::NAMES
+            .iter()
+            .filter(|setting| setting.starts_with(arg))
+            .map(|setting| format!("{}=", setting))
+            .collect(),
+        _ => Vec::new(),
+    };
+    candidates
+        .into_iter()
+        .map(|candidate| format!("{} {}", name, candidate))
+        .collect()
+}
+
+/// return the paths starting with `text`, directories end with a /
+fn complete_path(text: &str) -> Vec<String> {
+    let (dir, prefix) = match text.rfind('/') {
+        Some(i) => text.split_at(i + 1),
+        None => ("", text),
+    };
+    let entries = match std::fs::read_dir(if dir.is_empty() { "." } else { dir }) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut paths: Vec<String> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            // hidden files are only offered once a . is typed
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let slash = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{}{}{}", dir, name, slash))
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// The line being typed, along with the state of browsing the history and completing
+#[derive(Clone, Debug, Default)]
+pub struct CommandLine {
+    pub input: String,
+    // the entry of the history being shown, and what was typed before browsing it
+    history_index: Option<usize>,
+    draft: String,
+    // the lines tab cycles through, and the one shown
+    completions: Vec<String>,
+    completion: usize,
+}
+
+impl CommandLine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.stop_browsing();
+        self.input.push(c);
+    }
+
+    pub fn pop(&mut self) {
+        self.stop_browsing();
+        self.input.pop();
+    }
+
+    /// edit the line shown rather than the history entry or completion it came from
+    fn stop_browsing(&mut self) {
+        self.history_index = None;
+        self.completions.clear();
+    }
+
+    /// show the line in `history` before the one shown, `history` is oldest first
+    pub fn previous(&mut self, history: &[String]) {
+        let index = match self.history_index {
+            None if history.is_empty() => return,
+            None => {
+                self.draft = self.input.clone();
+                history.len() - 1
+            }
+            Some(0) => return,
+            Some(i) => i - 1,
+        };
+        self.completions.clear();
+        self.history_index = Some(index);
+        self.input = history[index].clone();
+    }
+
+    /// show the line in `history` after the one shown, or what was typed after the newest
+    pub fn next(&mut self, history: &[String]) {
+        self.completions.clear();
+        self.history_index = match self.history_index {
+            Some(i) if i + 1 < history.len() => {
+                self.input = history[i + 1].clone();
+                Some(i + 1)
+            }
+            Some(_) => {
+                self.input = std::mem::take(&mut self.draft);
+                None
+            }
+            None => None,
+        };
+    }
+
+    /// complete the last word. calling it again cycles through the other ways to complete it
+    pub fn complete(&mut self) {
+        // a single completion, like a directory, is completed further on the next tab
+        if self.completions.len() <= 1 {
+            self.completions = completions(&self.input);
+            self.completion = 0;
+        } else {
+            self.completion = (self.completion + 1) % self.completions.len();
+        }
+        if let Some(line) = self.completions.get(self.completion) {
+            self.input = line.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::renderer::LineNumbers;
+    use std::fs;
+
+    #[test]
+    fn test_parse_command_line() {
+        assert_eq!(parse("w"), Ok(Typed::Write(None)));
+        assert_eq!(
+            parse(" write  notes.txt "),
+            Ok(Typed::Write(Some("notes.txt".to_string())))
+        );
+        assert_eq!(parse("q!"), Ok(Typed::Quit { force: true }));
+        assert_eq!(parse("e src/main.rs"), Ok(Typed::Edit("src/main.rs".to_string())));
+        assert_eq!(parse("goto 120"), Ok(Typed::Goto(120)));
+        assert_eq!(parse("12"), Ok(Typed::Goto(12)));
+        assert_eq!(parse("set tabwidth=4"), Ok(Typed::Set(Setting::TabWidth(4))));
+        assert_eq!(
+            parse("set numbers relative"),
+            Ok(Typed::Set(Setting::Numbers(LineNumbers::Relative)))
+        );
+        assert_eq!(parse("toggle-wrap"), Ok(Typed::Run(Command::ToggleWrap)));
+
+        assert!(parse("goto").is_err());
+        assert!(parse("set tabwidth=0").is_err());
+        assert_eq!(parse("explode"), Err("unknown command explode".to_string()));
+    }
+
+    #[test]
+    fn test_command_line() {
+        let history = vec!["w".to_string(), "goto 3".to_string()];
+        let mut line = CommandLine::new();
+        line.push('g');
+        line.previous(&history);
+        assert_eq!(line.input, "goto 3");
+        line.previous(&history);
+        line.previous(&history);
+        assert_eq!(line.input, "w");
+        line.next(&history);
+        line.next(&history);
+        assert_eq!(line.input, "g");
+
+        // tab cycles through the commands starting with what was typed
+        line.push('r');
+        line.complete();
+        assert_eq!(line.input, "grow-down");
+        line.complete();
+        assert_eq!(line.input, "grow-left");
+        assert_eq!(completions("set t"), vec!["set tabwidth="]);
+
+        let dir = std::env::temp_dir().join(format!("rust-ed-complete-{}", std::process::id()));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("notes.txt"), "").unwrap();
+        fs::write(dir.join(".hidden"), "").unwrap();
+        let dir_name = dir.display().to_string();
+        assert_eq!(
+            completions(&format!("e {}/", dir_name)),
+            vec![format!("e {}/notes.txt", dir_name), format!("e {}/src/", dir_name)]
+        );
+        assert_eq!(
+            completions(&format!("w {}/.", dir_name)),
+            vec![format!("w {}/.hidden", dir_name)]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
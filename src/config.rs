@@ -0,0 +1,200 @@
+//! the user's config file. each line is a directive, blank lines and lines starting with # are
+//! ignored:
+//!
+//! ```text
+//! # quit with ctrl+q instead of ctrl+b
+//! unbind ctrl+b
+//! bind ctrl+q exit
+//! # start in vi mode, with tab stops every 2 columns
+//! set vi on
+//! set tabwidth 2
+//! ```
+
+use crate::keymap::{self, Command, Keymap};
+use crate::renderer::LineNumbers;
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+
+/// A problem with a line of the config file
+#[derive(Debug, PartialEq, Eq)]
+pub struct ConfigError {
+    /// the line number, counting from 1
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "config line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ConfigError {}
+
+/// An option changed with `set`, in the config file or on the command line
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Setting {
+    /// vi style modal editing
+    Vi(bool),
+    /// soft wrapping of long lines
+    Wrap(bool),
+    Numbers(LineNumbers),
+    /// the distance between tab stops
+    TabWidth(usize),
+    /// the rows kept between the cursor and the top and bottom of the view
+    ScrollOff(i32),
+    /// the columns kept between the cursor and the sides of the view
+    SideScrollOff(i32),
+}
+
+impl Setting {
+    /// the name of every setting
+    pub const NAMES: &'static [&'static str] = &[
+        "numbers",
+        "scrolloff",
+        "sidescrolloff",
+        "tabwidth",
+        "vi",
+        "wrap",
+    ];
+
+    /// read the setting called `name` with the value `value`
+    pub fn parse(name: &str, value: &str) -> Result<Setting, String> {
+        let switch = || match value {
+            "on" => Ok(true),
+            "off" => Ok(false),
+            _ => Err(format!("{} can be on or off, not {}", name, value)),
+        };
+        match name {
+            "vi" => Ok(Setting::Vi(switch()?)),
+            "wrap" => Ok(Setting::Wrap(switch()?)),
+            "numbers" => match value {
+                "off" => Ok(Setting::Numbers(LineNumbers::Off)),
+                "absolute" => Ok(Setting::Numbers(LineNumbers::Absolute)),
+                "relative" => Ok(Setting::Numbers(LineNumbers::Relative)),
+                _ => Err(format!("numbers can be off, absolute or relative, not {}", value)),
+            },
+            "tabwidth" => match value.parse() {
+                Ok(width) if width > 0 => Ok(Setting::TabWidth(width)),
+                _ => Err(format!("tabwidth has to be a number above 0, not {}", value)),
+            },
+            "scrolloff" | "sidescrolloff" => match value.parse() {
+                Ok(margin) if margin >= 0 && name == "scrolloff" => Ok(Setting::ScrollOff(margin)),
+                Ok(margin) if margin >= 0 => Ok(Setting::SideScrollOff(margin)),
+                _ => Err(format!("{} has to be a number of 0 or more, not {}", name, value)),
+            },
+            _ => Err(format!("unknown setting {}", name)),
+        }
+    }
+}
+
+/// Settings read from the config file
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    pub keymap: Keymap,
+    /// the settings in the order they were given
+    pub settings: Vec<Setting>,
+}
+
+impl Config {
+    /// return the path of the config file: `$RUST_ED_CONFIG` if it is set, otherwise
+    /// `rust-ed/config` in the user's config directory
+    pub fn path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os("RUST_ED_CONFIG") {
+            return Some(PathBuf::from(path));
+        }
+        let dir = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(dir.join("rust-ed").join("config"))
+    }
+
+    /// read the config file, using the defaults if there isn't one
+    pub fn load() -> Result<Config, Box<dyn Error>> {
+        let path = match Self::path() {
+            Some(path) if path.exists() => path,
+            _ => return Ok(Config::default()),
+        };
+        Ok(Self::parse(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// apply the directives in `text` on top of the defaults
+    pub fn parse(text: &str) -> Result<Config, ConfigError> {
+        let mut config = Config::default();
+        for (i, line) in text.lines().enumerate() {
+            let error = |message: String| ConfigError {
+                line: i + 1,
+                message,
+            };
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let key = |text: &str| {
+                keymap::parse_key(text).ok_or_else(|| error(format!("unknown key {}", text)))
+            };
+
+            match words.as_slice() {
+                [] => {}
+                [first, ..] if first.starts_with('#') => {}
+                ["bind", key_text, name] => {
+                    let command = Command::from_name(name)
+                        .ok_or_else(|| error(format!("unknown command {}", name)))?;
+                    config.keymap.bind(key(key_text)?, command);
+                }
+                ["unbind", key_text] => config.keymap.unbind(key(key_text)?),
+                ["set", name, value] => {
+                    config.settings.push(Setting::parse(name, value).map_err(&error)?)
+                }
+                _ => return Err(error(format!("can't read \"{}\"", line.trim()))),
+            }
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    #[test]
+    fn test_parse_config() {
+        let config =
+            Config::parse("# quit with ctrl+q\n\nunbind ctrl+b\n  bind ctrl+q exit\nset vi on\n")
+                .unwrap();
+        assert_eq!(config.settings, vec![Setting::Vi(true)]);
+        let keymap = &config.keymap;
+        assert_eq!(
+            keymap.get(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::CONTROL)),
+            Some(Command::Exit)
+        );
+        assert_eq!(
+            keymap.get(KeyEvent::new(KeyCode::Char('b'), KeyModifiers::CONTROL)),
+            None
+        );
+        // the defaults are kept
+        assert_eq!(
+            keymap.get(KeyEvent::from(KeyCode::F(1))),
+            Some(Command::Help)
+        );
+
+        let error = Config::parse("bind ctrl+q exit\nbind ctrl+e explode").unwrap_err();
+        assert_eq!(error.to_string(), "config line 2: unknown command explode");
+        assert!(Config::parse("bind hyper+q exit").is_err());
+        assert!(Config::parse("set tabwidth").is_err());
+        let error = Config::parse("set vi maybe").unwrap_err();
+        assert_eq!(error.to_string(), "config line 1: vi can be on or off, not maybe");
+        assert_eq!(
+            Config::parse("set tabwidth 2").unwrap().settings,
+            vec![Setting::TabWidth(2)]
+        );
+        assert_eq!(
+            Config::parse("set scrolloff 0\nset sidescrolloff 8").unwrap().settings,
+            vec![Setting::ScrollOff(0), Setting::SideScrollOff(8)]
+        );
+        let error = Config::parse("set scrolloff -1").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "config line 1: scrolloff has to be a number of 0 or more, not -1"
+        );
+    }
+}
@@ -1,41 +1,46 @@
 //! editor state. controls operations such as reading and writing text.
 #![allow(unused_variables, dead_code)]
 
-// TODO: Make the write function erase the current selection before beginning a write
-
-use std::collections::VecDeque;
-use crossterm::style::{Color, style, StyledContent};
-use std::fmt::{Formatter, Error};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use crate::grid::Grid;
+use crate::history::{Change, EditKind, History, Snapshot};
+use crate::line_ending::{self, LineEnding, LineEndingCount};
+use crate::syntax::{Language, Syntax};
+use crate::unicode;
+use crossterm::style::{Color, style};
 
 /// Information for a particular character cell.
 /// Contains color values and other metadata
 #[derive(Copy, Clone)]
 pub struct CharCel {
     pub char: char,
-    fg_on: bool,
-    bg_on: bool,
-    fg: Color,
-    bg: Color,
+    pub fg_on: bool,
+    pub bg_on: bool,
+    pub fg: Color,
+    pub bg: Color,
 }
 
 
 impl Default for CharCel {
     fn default() -> Self {
-        return Self {
+        Self {
             char: '0',
             fg_on: false,
             bg_on: false,
             fg: Color::White,
             bg: Color::Black,
-        };
+        }
     }
 }
 
 impl From<char> for CharCel {
     fn from(a: char) -> CharCel {
-        let mut x = Self::default();
-        x.char = a;
-        return x;
+        Self {
+            char: a,
+            ..Self::default()
+        }
     }
 }
 
@@ -54,7 +59,7 @@ pub struct StringCel {
 
 impl From<Vec<CharCel>> for StringCel {
     fn from(vec: Vec<CharCel>) -> Self {
-        return Self {
+        Self {
             vec
         }
     }
@@ -62,8 +67,9 @@ impl From<Vec<CharCel>> for StringCel {
 
 impl std::fmt::Display for StringCel{
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.vec.iter()
-           .map(|x| write!(f, "{}", style(x.char).on(x.bg).with(x.fg)));
+        for x in self.vec.iter() {
+            write!(f, "{}", style(x.char).on(x.bg).with(x.fg))?;
+        }
         Ok(())
     }
 }
@@ -74,8 +80,20 @@ impl std::fmt::Display for StringCel{
 //     }
 // }
 
-// TODO: create a trait for operations on a grid
-type Grid = Vec<Vec<CharCel>>;
+/// A background color drawn behind a range of cells, such as a search match.
+/// `end` is exclusive
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Highlight {
+    pub start: Vector2,
+    pub end: Vector2,
+    pub bg: Color,
+}
+
+impl Highlight {
+    pub fn contains(&self, location: Vector2) -> bool {
+        location >= self.start && location < self.end
+    }
+}
 
 /// Very simple vector implementation
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -96,7 +114,7 @@ impl Vector2 {
 
 impl From<&Vector2> for Vector2 {
     fn from(a: &Vector2) -> Vector2 {
-        a.clone()
+        *a
     }
 }
 
@@ -126,12 +144,81 @@ impl From<(i32, i32)> for Vector2 {
     }
 }
 
+/// background of selected text
+const SELECTION_COLOR: Color = Color::DarkBlue;
+/// background of the cell under each cursor besides the primary one
+pub const SECONDARY_CURSOR_COLOR: Color = Color::Grey;
+
+/// A cursor besides the primary one, with the start of its selection if it has one
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Cursor {
+    location: Vector2,
+    anchor: Option<Vector2>,
+}
+
+impl Cursor {
+    /// return the selected range in order, which is empty without a selection
+    fn range(&self) -> (Vector2, Vector2) {
+        let anchor = self.anchor.unwrap_or(self.location);
+        (anchor.min(self.location), anchor.max(self.location))
+    }
+}
+
+/// A rectangle of text selected by screen column, which can reach past the end of short lines.
+/// the corners are given as (screen column, line)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Block {
+    pub anchor: Vector2,
+    /// the corner which moves as the selection is extended
+    pub head: Vector2,
+}
+
+impl Block {
+    /// return the first screen column of the block and the one after its last
+    pub fn columns(&self) -> (i32, i32) {
+        (self.anchor.x().min(self.head.x()), self.anchor.x().max(self.head.x()))
+    }
+
+    /// return the first and last line of the block
+    pub fn lines(&self) -> (i32, i32) {
+        (self.anchor.y().min(self.head.y()), self.anchor.y().max(self.head.y()))
+    }
+
+    /// return the block moved `dx` screen columns
+    fn shift(self, dx: i32) -> Self {
+        Block {
+            anchor: self.anchor.add((dx, 0)),
+            head: self.head.add((dx, 0)),
+        }
+    }
+}
+
 /// Editor state information
 pub struct Editor {
     buffer: Grid,
+    // the primary cursor, which the terminal cursor is drawn at
     cursor: Vector2,
     select_start: Option<Vector2>,
     selecting: bool,
+    // every other cursor. edits made at one cursor shift the others along with the text
+    cursors: Vec<Cursor>,
+    // true while running an operation at each cursor, which it then only does once
+    in_each_cursor: bool,
+    // the block selection, which has a cursor on each of its lines
+    block: Option<Block>,
+    // file the buffer was loaded from and will be saved to
+    path: Option<PathBuf>,
+    // true when the buffer has changed since it was last loaded or saved
+    modified: bool,
+    history: History,
+    // line break inserted for new lines
+    line_ending: LineEnding,
+    // background colors applied on top of the text
+    highlights: Vec<Highlight>,
+    // foreground colors from syntax highlighting
+    syntax: Syntax,
+    // the distance between tab stops
+    tab_width: usize,
 }
 
 /// Create an editor for types which implement Into<String>
@@ -140,29 +227,144 @@ impl<T: Into<String>> From<T> for Editor {
         let mut editor = Editor::new();
         let s = s.into();
 
-        editor.buffer = s
-            .lines()
-            .map(|x| x.chars().map(|x| CharCel::from(x)).collect())
-            .collect();
-
-        // include the extra new line at the end, .lines omits this.
-        if s.ends_with('\n') || s.ends_with("\r\n") {
-            editor.buffer.push(Vec::new());
-        }
+        // line breaks are kept as they are, new lines use the most common style
+        editor.buffer = Grid::from(s.as_str());
+        editor.line_ending = editor
+            .buffer
+            .line_ending_count()
+            .dominant()
+            .unwrap_or_default();
 
         editor
     }
 }
 
+impl Default for Editor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Editor {
     /// Create a new editor with the default options
     pub fn new() -> Self {
-        return Self {
+        Self {
             buffer: Grid::new(),
             cursor: Vector2(0, 0),
             select_start: None,
             selecting: false,
+            cursors: Vec::new(),
+            in_each_cursor: false,
+            block: None,
+            path: None,
+            modified: false,
+            history: History::new(),
+            line_ending: LineEnding::default(),
+            highlights: Vec::new(),
+            syntax: Syntax::default(),
+            tab_width: unicode::TAB_WIDTH,
+        }
+    }
+
+    /// Create an editor from the file at `path`.
+    /// If the file does not exist yet, the editor starts empty and will create it on save.
+    ///
+    /// # Errors
+    /// Any I/O error other than `NotFound` encountered while reading the file
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut editor = match load(path) {
+            Ok(text) => Editor::from(text),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Editor::new(),
+            Err(e) => return Err(e),
+        };
+        editor.path = Some(path.to_path_buf());
+        editor.set_language(Language::from_path(path));
+        Ok(editor)
+    }
+
+    /// Write the buffer back to the file it was opened from
+    ///
+    /// # Errors
+    /// If the editor has no file path, or writing the file fails
+    pub fn save(&mut self) -> io::Result<()> {
+        let path = self.path.clone().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "no file name")
+        })?;
+        self.save_as(path)
+    }
+
+    /// Write the buffer to `path` and make it the file associated with the editor
+    ///
+    /// The contents are written to a temporary file next to `path` which is then renamed over it,
+    /// so a failed write never leaves a truncated file behind.
+    pub fn save_as(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        store(path, &self.to_string())?;
+        self.path = Some(path.to_path_buf());
+        self.modified = false;
+        if self.syntax.language().is_none() {
+            self.set_language(Language::from_path(path));
+        }
+        Ok(())
+    }
+
+    /// return the path of the file being edited
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// return the language used for syntax highlighting
+    pub fn language(&self) -> Option<Language> {
+        self.syntax.language()
+    }
+
+    /// highlight the buffer as `language`, or turn highlighting off
+    pub fn set_language(&mut self, language: Option<Language>) {
+        self.syntax = Syntax::new(language);
+        self.syntax.reset(&self.buffer);
+    }
+
+    /// return true if the buffer has unsaved changes
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    /// return the line ending used for new lines
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    /// count the line endings of each style in the buffer
+    pub fn line_ending_count(&self) -> LineEndingCount {
+        self.buffer.line_ending_count()
+    }
+
+    /// Convert every line break in the buffer to `style` and use it for new lines.
+    /// the conversion is undone as a single step
+    pub fn set_line_ending(&mut self, style: LineEnding) {
+        self.line_ending = style;
+        self.begin_edit(EditKind::Other);
+
+        // a lone \r and a \n side by side would read as one break, so each break is converted
+        // next to those already converted: top down to \n and bottom up to \r
+        let rows = 0..self.buffer.len();
+        let rows: Vec<usize> = match style {
+            LineEnding::Cr => rows.rev().collect(),
+            _ => rows.collect(),
         };
+        for y in rows {
+            match self.buffer.line_ending(y) {
+                Some(ending) if ending != style => {
+                    let end = Vector2(self.buffer.row_len(y).unwrap() as i32, y as i32);
+                    self.remove_text(end, Vector2(0, y as i32 + 1));
+                    self.insert_text(end, style.as_str());
+                }
+                _ => {}
+            }
+        }
+
+        self.end_edit();
     }
 
     pub fn cursor_pos(&self) -> Vector2 {
@@ -171,19 +373,76 @@ impl Editor {
 
     /// return the length of the current line or zero if not currently on a line
     pub fn line_len(&self) -> usize {
-        self.buffer
-            .get(self.cursor.y() as usize)
-            .map_or(0, |row| row.len())
+        self.buffer.row_len(self.cursor.y() as usize).unwrap_or(0)
     }
 
-    /// Move the cursor towards the given vector
+    /// Move every cursor towards the given vector.
+    /// the horizontal component counts grapheme clusters, and moving between rows keeps
+    /// the cursor in the same screen column where possible
     ///
     /// # Returns
-    /// A vector storing the position of the cursor after clamping it to valid coordinates
+    /// A vector storing the position of the primary cursor after clamping it to valid coordinates
     pub fn move_cursor(&mut self, direction: impl Into<Vector2>) -> Vector2 {
-        let new_pos = self.clamp_vector(self.cursor.add(&direction.into()));
+        let direction = direction.into();
+        self.block = None;
+        self.each_cursor(|editor| {
+            editor.move_primary(direction);
+        });
+        self.cursor
+    }
+
+    /// move the primary cursor towards the given vector
+    fn move_primary(&mut self, direction: Vector2) -> Vector2 {
+        let Vector2(dx, dy) = direction;
+        let mut pos = self.cursor;
+
+        if dy != 0 {
+            let column = self.display_column(pos);
+            let y = (pos.1 + dy).clamp(0, self.buffer.len() as i32 - 1);
+            pos = Vector2(self.column_at_display(y, column), y);
+        }
+
+        if dx != 0 {
+            let row = self.get_line(pos.1).unwrap_or_default();
+            pos.0 = unicode::step(&row, pos.0.max(0) as usize, dx) as i32;
+        }
+
+        let new_pos = self.clamp_vector(pos);
         self.cursor = new_pos;
-        return new_pos;
+        new_pos
+    }
+
+    /// return the text of row `line` without the line break
+    pub fn get_line(&self, line: i32) -> Option<String> {
+        if line < 0 {
+            return None;
+        }
+        self.buffer.row_text(line as usize)
+    }
+
+    /// return the screen column, relative to the start of the row, at which `location` is drawn
+    pub fn display_column(&self, location: Vector2) -> i32 {
+        let Vector2(x, y) = location;
+        let row = self.get_line(y).unwrap_or_default();
+        unicode::display_col(&row, x.max(0) as usize, self.tab_width) as i32
+    }
+
+    /// return the buffer column of the grapheme cluster drawn at screen column `column` of row `y`
+    pub fn column_at_display(&self, y: i32, column: i32) -> i32 {
+        let column = column.max(0);
+        match self.get_line(y) {
+            Some(row) => unicode::col_at_display(&row, column as usize, self.tab_width) as i32,
+            None => column,
+        }
+    }
+
+    /// return the distance between the tab stops tabs are drawn up to
+    pub fn tab_width(&self) -> usize {
+        self.tab_width
+    }
+
+    pub fn set_tab_width(&mut self, width: usize) {
+        self.tab_width = width.max(1);
     }
 
     /// Set the cursor position to a specific coordinate
@@ -191,6 +450,7 @@ impl Editor {
     /// # Returns
     /// A vector storing the position of the cursor after clamping it to valid coordinates
     pub fn set_cursor(&mut self, location: impl Into<Vector2>) -> Vector2 {
+        self.block = None;
         let new_pos = self.clamp_vector(location.into());
         self.cursor = new_pos;
         new_pos
@@ -217,108 +477,611 @@ impl Editor {
     fn clamp_to_column(&self, v: Vector2) -> Vector2 {
         let Vector2(x, y) = v;
 
-        let row = self.buffer.get(y as usize);
-
         // If there is no row at the given y value after clamping, the buffer is probably empty
-        if row.is_none() {
-            return Vector2(0, 0);
-        }
-
-        let row = row.unwrap();
-        let len = row.len() as i32;
+        let len = match self.buffer.row_len(y as usize) {
+            Some(len) => len as i32,
+            None => return Vector2(0, 0),
+        };
 
         if x < 0 {
             Vector2(0, y)
         } else if x >= len {
             Vector2(len, y)
-        } else {
+        } else if self.buffer.row_is_ascii(y as usize) {
             v
+        } else {
+            // never leave the cursor in the middle of a grapheme cluster
+            let row = self.buffer.row_text(y as usize).unwrap_or_default();
+            Vector2(unicode::floor_boundary(&row, x as usize) as i32, y)
         }
     }
 
-    /// begin selecting from the current location of the cursor
+    /// begin selecting from the current location of every cursor
     pub fn begin_select(&mut self) {
-        self.begin_select_at(self.cursor)
+        self.block = None;
+        self.each_cursor(|editor| editor.begin_select_at(editor.cursor))
     }
 
     /// set the selection to start from the given coordinate
     pub fn begin_select_at(&mut self, loc: impl Into<Vector2>) {
-        self.buffer
         self.select_start = Some(loc.into());
         self.selecting = true;
     }
 
-    /// Clear the currently selected location.
+    /// Clear the selection of every cursor.
     pub fn clear_selection(&mut self) {
+        self.block = None;
         self.select_start = None;
         self.selecting = false;
+        for cursor in &mut self.cursors {
+            cursor.anchor = None;
+        }
     }
 
-    /// copy the selected text
-    pub fn copy(&self) -> Option<Vec<CharCel>> {
-        if self.selecting {
-            Some(self.copy_range(self.select_start.unwrap(), self.cursor))
+    /// select the text from `start` to `end`, leaving the cursor at `end`
+    pub fn select_range(&mut self, start: impl Into<Vector2>, end: impl Into<Vector2>) {
+        let start = self.clamp_vector(start.into());
+        self.set_cursor(end);
+        self.begin_select_at(start);
+    }
+
+    /// return the start and end of the word at `location`, a run of word characters,
+    /// punctuation or space within its line. past the end of a line it is the run before
+    pub fn word_at(&self, location: impl Into<Vector2>) -> (Vector2, Vector2) {
+        let Vector2(x, y) = self.clamp_vector(location.into());
+        let chars: Vec<char> = self.get_line(y).unwrap_or_default().chars().collect();
+        if chars.is_empty() {
+            return (Vector2(0, y), Vector2(0, y));
+        }
+        let x = (x as usize).min(chars.len() - 1);
+        let class = unicode::char_class(chars[x]);
+        let same = |c: &char| unicode::char_class(*c) == class;
+        let start = chars[..x].iter().rposition(|c| !same(c)).map_or(0, |i| i + 1);
+        let end = chars[x..].iter().position(|c| !same(c)).map_or(chars.len(), |i| x + i);
+        (Vector2(start as i32, y), Vector2(end as i32, y))
+    }
+
+    /// return where moving by a word to the left (negative `direction`) or right of `location`
+    /// stops: the start of the word before or the end of the word after. a line break is
+    /// stepped over on its own
+    pub fn word_boundary(&self, location: impl Into<Vector2>, direction: i32) -> Vector2 {
+        let Vector2(x, y) = self.clamp_vector(location.into());
+        let chars: Vec<char> = self.get_line(y).unwrap_or_default().chars().collect();
+        let class = |i: usize| unicode::char_class(chars[i]);
+        let mut x = x as usize;
+        if direction < 0 {
+            if x == 0 {
+                let previous = self.clamp_vector(Vector2(i32::MAX, y - 1));
+                return if y > 0 { previous } else { Vector2(0, 0) };
+            }
+            while x > 0 && class(x - 1) == unicode::CharClass::Space {
+                x -= 1;
+            }
+            if x > 0 {
+                let word = class(x - 1);
+                while x > 0 && class(x - 1) == word {
+                    x -= 1;
+                }
+            }
         } else {
-            None
+            if x >= chars.len() {
+                let next = y as usize + 1 < self.line_count();
+                return if next { Vector2(0, y + 1) } else { Vector2(x as i32, y) };
+            }
+            while x < chars.len() && class(x) == unicode::CharClass::Space {
+                x += 1;
+            }
+            if x < chars.len() {
+                let word = class(x);
+                while x < chars.len() && class(x) == word {
+                    x += 1;
+                }
+            }
         }
+        Vector2(x as i32, y)
     }
 
-    /// cut the selected text
-    pub fn cut(&mut self) -> Option<Vec<CharCel>> {
-        if self.selecting {
-            self.selecting = false;
-            Some(self.cut_range(self.select_start.unwrap(), self.cursor))
+    /// return the start and end of line `y` including its line break
+    pub fn line_range(&self, y: i32) -> (Vector2, Vector2) {
+        let start = self.clamp_vector(Vector2(0, y));
+        let end = if (start.y() as usize) + 1 < self.line_count() {
+            Vector2(0, start.y() + 1)
         } else {
-            None
+            Vector2(self.get_line(start.y()).unwrap_or_default().chars().count() as i32, start.y())
+        };
+        (start, end)
+    }
+
+    /// copy the selected text. the text of each selection is joined by line breaks, in the
+    /// order the selections are in the buffer
+    pub fn copy(&self) -> Option<Vec<CharCel>> {
+        if let Some(block) = self.block {
+            return Some(self.block_text(block));
         }
+        let mut ranges: Vec<(Vector2, Vector2)> = self
+            .all_cursors()
+            .iter()
+            .filter(|cursor| cursor.anchor.is_some())
+            .map(Cursor::range)
+            .collect();
+        if ranges.is_empty() {
+            return None;
+        }
+        ranges.sort();
+        Some(join_lines(ranges.into_iter().map(|(from, to)| self.copy_range(from, to))))
     }
 
-    /// return the character at location
-    pub fn get_cell(&self, location: impl Into<Vector2>) -> Option<CharCel> {
-        let location = location.into();
+    /// cut the selected text at every cursor, returning it joined like `copy`
+    pub fn cut(&mut self) -> Option<Vec<CharCel>> {
+        if let Some(block) = self.block {
+            let text = self.block_text(block);
+            self.begin_edit(EditKind::Other);
+            self.remove_block(block);
+            self.end_edit();
+            return Some(text);
+        }
+        if self.all_cursors().iter().all(|cursor| cursor.anchor.is_none()) {
+            return None;
+        }
+        let mut pieces = Vec::new();
+        self.begin_edit(EditKind::Other);
+        self.each_cursor(|editor| {
+            if let Some(start) = editor.select_start.filter(|_| editor.selecting) {
+                editor.selecting = false;
+                let location = start.min(editor.cursor);
+                let index = editor.location_to_index(location);
+                pieces.push((index, editor.cut_range(start, editor.cursor)));
+            }
+        });
+        self.end_edit();
+        // a cut shifts the text after it, but not the text before
+        pieces.sort_by_key(|(index, _)| *index);
+        Some(join_lines(pieces.into_iter().map(|(_, cells)| cells)))
+    }
 
-        self.buffer
-            .get(location.y() as usize)
-            .map(|row| row.get(location.x() as usize).map(|x| x.clone()))
-            .and_then(|x| match x {
-                Some(x) => Some(x),
-                None => None,
+    /// run `f` for each cursor in turn, with that cursor as the primary one.
+    /// cursors which end up in the same place are merged afterwards.
+    /// an operation made of others which run at each cursor still only runs once per cursor
+    pub fn each_cursor(&mut self, mut f: impl FnMut(&mut Self)) {
+        if self.cursors.is_empty() || self.in_each_cursor {
+            f(self);
+            return;
+        }
+        self.in_each_cursor = true;
+        f(self);
+        let mut i = 0;
+        while i < self.cursors.len() {
+            self.swap_cursor(i);
+            f(self);
+            self.swap_cursor(i);
+            i += 1;
+        }
+        self.in_each_cursor = false;
+        self.merge_cursors();
+    }
+
+    /// exchange the primary cursor with the secondary cursor at `index`
+    fn swap_cursor(&mut self, index: usize) {
+        let primary = self.primary();
+        let other = std::mem::replace(&mut self.cursors[index], primary);
+        self.cursor = other.location;
+        self.select_start = other.anchor;
+        self.selecting = other.anchor.is_some();
+    }
+
+    fn primary(&self) -> Cursor {
+        Cursor {
+            location: self.cursor,
+            anchor: self.select_start.filter(|_| self.selecting),
+        }
+    }
+
+    /// return true if any cursor's selection covers `location`
+    fn is_selected(&self, location: Vector2) -> bool {
+        let contains = |cursor: Cursor| {
+            let (start, end) = cursor.range();
+            location >= start && location < end
+        };
+        contains(self.primary()) || self.cursors.iter().any(|cursor| contains(*cursor))
+    }
+
+    /// return every cursor, the primary one first
+    fn all_cursors(&self) -> Vec<Cursor> {
+        std::iter::once(self.primary()).chain(self.cursors.iter().copied()).collect()
+    }
+
+    /// merge cursors in the same place and selections which overlap.
+    /// the primary cursor keeps its place as the primary one
+    fn merge_cursors(&mut self) {
+        if self.cursors.is_empty() {
+            return;
+        }
+        let mut cursors: Vec<(Cursor, bool)> = self
+            .all_cursors()
+            .into_iter()
+            .enumerate()
+            .map(|(i, cursor)| (cursor, i == 0))
+            .collect();
+        cursors.sort_by_key(|(cursor, _)| cursor.range());
+
+        let mut merged: Vec<(Cursor, bool)> = Vec::new();
+        for (cursor, primary) in cursors {
+            let (start, end) = cursor.range();
+            match merged.last_mut() {
+                Some((last, last_primary))
+                    if start < last.range().1 || start == last.range().0 =>
+                {
+                    // grow the earlier selection to cover both, keeping its direction
+                    let (last_start, last_end) = last.range();
+                    let end = end.max(last_end);
+                    if last.anchor.is_some() || cursor.anchor.is_some() {
+                        let forwards = last.anchor.is_none_or(|anchor| anchor <= last.location);
+                        *last = if forwards {
+                            Cursor { location: end, anchor: Some(last_start) }
+                        } else {
+                            Cursor { location: last_start, anchor: Some(end) }
+                        };
+                    }
+                    *last_primary |= primary;
+                }
+                _ => merged.push((cursor, primary)),
+            }
+        }
+
+        let index = merged.iter().position(|(_, primary)| *primary).unwrap_or(0);
+        let (primary, _) = merged.remove(index);
+        self.cursor = primary.location;
+        self.select_start = primary.anchor;
+        self.selecting = primary.anchor.is_some();
+        self.cursors = merged.into_iter().map(|(cursor, _)| cursor).collect();
+    }
+
+    /// add a cursor at `location`, which becomes the primary cursor
+    pub fn add_cursor(&mut self, location: impl Into<Vector2>) {
+        self.block = None;
+        let primary = self.primary();
+        self.cursors.push(primary);
+        self.cursor = self.clamp_vector(location.into());
+        self.select_start = None;
+        self.selecting = false;
+        self.merge_cursors();
+    }
+
+    /// add a cursor on the line above (negative `dy`) or below the primary cursor,
+    /// in the same screen column
+    ///
+    /// # Returns
+    /// false if there is no line there
+    pub fn add_cursor_vertically(&mut self, dy: i32) -> bool {
+        let y = self.cursor.y() + dy;
+        if y < 0 || y >= self.buffer.len() as i32 {
+            return false;
+        }
+        let column = self.display_column(self.cursor);
+        self.add_cursor((self.column_at_display(y, column), y));
+        true
+    }
+
+    /// select the next occurrence of the primary selection with a new cursor, searching from
+    /// the end of the selection and wrapping around the end of the buffer
+    ///
+    /// # Returns
+    /// false if there is no selection or no other occurrence of it
+    pub fn add_cursor_at_next_match(&mut self) -> bool {
+        let (start, end) = match self.selection_range() {
+            Some((start, end)) if start != end => (start, end),
+            _ => return false,
+        };
+        let needle: String = self.copy_range(start, end).into_iter().map(|c| c.char).collect();
+        let text = self.buffer.to_string();
+        let taken: Vec<usize> = self
+            .all_cursors()
+            .iter()
+            .map(|cursor| self.location_to_index(cursor.range().0))
+            .collect();
+
+        // char index of every occurrence, starting after the selection
+        let from = self.location_to_index(end);
+        let mut matches: Vec<usize> = Vec::new();
+        let mut chars = 0;
+        let mut last_byte = 0;
+        for (byte, _) in text.match_indices(needle.as_str()) {
+            chars += text[last_byte..byte].chars().count();
+            last_byte = byte;
+            matches.push(chars);
+        }
+        let next = matches
+            .iter()
+            .filter(|index| **index >= from)
+            .chain(matches.iter().filter(|index| **index < from))
+            .find(|index| !taken.contains(index));
+
+        let index = match next {
+            Some(index) => *index,
+            None => return false,
+        };
+        let length = needle.chars().count();
+        let primary = self.primary();
+        self.cursors.push(primary);
+        self.select_start = Some(self.index_to_location(index));
+        self.selecting = true;
+        self.cursor = self.index_to_location(index + length);
+        self.merge_cursors();
+        true
+    }
+
+    /// return the block selection
+    pub fn block(&self) -> Option<Block> {
+        self.block
+    }
+
+    /// start a block selection at the primary cursor
+    pub fn begin_block(&mut self) {
+        let corner = Vector2(self.display_column(self.cursor), self.cursor.y());
+        self.cursors.clear();
+        self.select_block(Block {
+            anchor: corner,
+            head: corner,
+        });
+    }
+
+    /// move the moving corner of the block selection to `head`, given as (screen column, line).
+    /// a block is started at the primary cursor if there isn't one
+    pub fn extend_block(&mut self, head: impl Into<Vector2>) {
+        if self.block.is_none() {
+            self.begin_block();
+        }
+        let Vector2(x, y) = head.into();
+        let anchor = self.block.map_or(Vector2(0, 0), |block| block.anchor);
+        let head = Vector2(x.max(0), y.clamp(0, self.buffer.len() as i32 - 1));
+        self.select_block(Block { anchor, head });
+    }
+
+    /// make `block` the selection, with a cursor selecting each of its lines
+    fn select_block(&mut self, block: Block) {
+        let (left, right) = block.columns();
+        let (top, bottom) = block.lines();
+        let forwards = block.head.x() >= block.anchor.x();
+        let mut cursors: Vec<Cursor> = (top..=bottom)
+            .map(|y| {
+                let start = Vector2(self.column_at_display(y, left), y);
+                let end = Vector2(self.column_at_display(y, right), y);
+                let (anchor, location) = if forwards { (start, end) } else { (end, start) };
+                Cursor {
+                    location,
+                    anchor: Some(anchor).filter(|anchor| *anchor != location),
+                }
             })
+            .collect();
+
+        // the primary cursor is on the line of the moving corner
+        let primary = cursors.remove((block.head.y() - top) as usize);
+        self.cursor = primary.location;
+        self.select_start = primary.anchor;
+        self.selecting = primary.anchor.is_some();
+        self.cursors = cursors;
+        self.block = Some(block);
     }
 
-    pub fn get_row(&self, line: i32) -> Option<&Vec<CharCel>> {
-        self.buffer.get(line as usize)
+    /// return the char columns of the part of line `y` in the columns from `left` to `right`
+    fn block_row(&self, y: i32, left: i32, right: i32) -> (Vector2, Vector2) {
+        (
+            Vector2(self.column_at_display(y, left), y),
+            Vector2(self.column_at_display(y, right), y),
+        )
     }
 
-    /// Copy the text at location `from` to location `to`
-    pub fn copy_range<T: Into<Vector2>>(&self, from: T, to: T) -> Vec<CharCel> {
-        use std::cmp::{max, min};
-        let (from, to) = (self.clamp_vector(from.into()), self.clamp_vector(to.into()));
-        let start = min(from, to);
-        let end = max(from, to);
+    /// return the text in `block`, each line padded with spaces to the width of the block
+    fn block_text(&self, block: Block) -> Vec<CharCel> {
+        let (left, right) = block.columns();
+        let (top, bottom) = block.lines();
+        join_lines((top..=bottom).map(|y| {
+            let (start, end) = self.block_row(y, left, right);
+            let mut cells = self.copy_range(start, end);
+            let width = self.display_column(end) - self.display_column(start);
+            cells.extend((width..right - left).map(|_| CharCel::from(' ')));
+            cells
+        }))
+    }
+
+    /// remove the text in `block`, leaving an empty block at its left edge
+    fn remove_block(&mut self, block: Block) -> Block {
+        let (left, right) = block.columns();
+        let (top, bottom) = block.lines();
+        for y in top..=bottom {
+            let (start, end) = self.block_row(y, left, right);
+            self.remove_text(start, end);
+        }
+        let block = Block {
+            anchor: Vector2(left, block.anchor.y()),
+            head: Vector2(left, block.head.y()),
+        };
+        self.select_block(block);
+        block
+    }
+
+    /// type `content` on every line of `block`, replacing its text.
+    /// lines too short to reach the block are padded with spaces
+    fn write_block(&mut self, block: Block, content: char) {
+        self.begin_edit(EditKind::Typing);
+        let block = self.remove_block(block);
+        let (left, _) = block.columns();
+        let (top, bottom) = block.lines();
+        for y in top..=bottom {
+            let end = Vector2(self.buffer.row_len(y as usize).unwrap_or(0) as i32, y);
+            let width = self.display_column(end);
+            if width < left {
+                self.insert_text(end, &" ".repeat((left - width) as usize));
+            }
+            let x = self.column_at_display(y, left);
+            self.insert_text(Vector2(x, y), content.encode_utf8(&mut [0; 4]));
+        }
+        let width = unicode::width(content.encode_utf8(&mut [0; 4])) as i32;
+        self.select_block(block.shift(width));
+        self.end_edit();
+    }
+
+    /// delete the text in `block`, or the column before it if it is empty
+    fn delete_block(&mut self, block: Block) {
+        self.begin_edit(EditKind::Other);
+        let (left, right) = block.columns();
+        if left < right {
+            self.remove_block(block);
+        } else if left > 0 {
+            let (top, bottom) = block.lines();
+            // the block moves left by the widest cluster deleted
+            let mut width = 1;
+            for y in top..=bottom {
+                // lines which don't reach the block only have virtual spaces to delete
+                let x = self.column_at_display(y, left);
+                if self.display_column(Vector2(x, y)) == left {
+                    let row = self.get_line(y).unwrap_or_default();
+                    let before = Vector2(unicode::step(&row, x as usize, -1) as i32, y);
+                    width = width.max(left - self.display_column(before));
+                    self.remove_text(before, Vector2(x, y));
+                }
+            }
+            self.select_block(block.shift(-width));
+        }
+        self.end_edit();
+    }
+
+    /// remove every cursor besides the primary one
+    pub fn clear_cursors(&mut self) {
+        self.block = None;
+        self.cursors.clear();
+    }
+
+    /// return the number of cursors, including the primary one
+    pub fn cursor_count(&self) -> usize {
+        self.cursors.len() + 1
+    }
+
+    /// return true if a cursor besides the primary one is at `location`
+    pub fn has_secondary_cursor(&self, location: Vector2) -> bool {
+        self.cursors.iter().any(|c| c.location == location)
+    }
+
+    /// return the char index of each secondary cursor and its anchor
+    fn cursor_indices(&self) -> Vec<(usize, Option<usize>)> {
+        let index = |location| self.buffer.to_index(location);
+        self.cursors.iter().map(|c| (index(c.location), c.anchor.map(index))).collect()
+    }
+
+    /// move the secondary cursors to the indices from `cursor_indices`, passed through `shift`
+    fn shift_cursors(
+        &mut self,
+        indices: Vec<(usize, Option<usize>)>,
+        shift: impl Fn(usize) -> usize,
+    ) {
+        let buffer = &self.buffer;
+        for (cursor, (location, anchor)) in self.cursors.iter_mut().zip(indices) {
+            cursor.location = buffer.to_location(shift(location));
+            cursor.anchor = anchor.map(|anchor| buffer.to_location(shift(anchor)));
+        }
+    }
+
+    /// return the character at location, colored by its syntax and any highlight covering it
+    pub fn get_cell(&self, location: impl Into<Vector2>) -> Option<CharCel> {
+        let location = location.into();
+        let mut cell = self.buffer.cell(location)?;
+        if let Some(token) = self.syntax.token_at(location.x() as usize, location.y() as usize) {
+            cell.fg_on = true;
+            cell.fg = token.color();
+        }
+        if let Some(highlight) = self.highlights.iter().find(|h| h.contains(location)) {
+            cell.bg_on = true;
+            cell.bg = highlight.bg;
+        } else if self.is_selected(location) {
+            cell.bg_on = true;
+            cell.bg = SELECTION_COLOR;
+        }
+        // the terminal's cursor only shows the primary cursor, the others are drawn
+        if self.has_secondary_cursor(location) {
+            cell.bg_on = true;
+            cell.bg = SECONDARY_CURSOR_COLOR;
+        }
+        Some(cell)
+    }
 
-        let mut data = Vec::new();
+    /// replace the highlighted ranges. earlier highlights take priority where they overlap
+    pub fn set_highlights(&mut self, highlights: Vec<Highlight>) {
+        self.highlights = highlights;
+    }
 
-        let mut position = start.clone();
-        while position < end {
-            println!("{:?}", position);
-            let row = self.buffer.get(position.1 as usize).unwrap();
+    pub fn highlights(&self) -> &[Highlight] {
+        &self.highlights
+    }
 
-            // move to the next row when the end of a line has been reached
-            if position.0 as usize == row.len() {
-                position = position.add(&Vector2(0, 1));
-                position.0 = 0;
-                data.push(CharCel::from('\n'));
-                continue;
+    /// return a copy of the cells in row `line`
+    pub fn get_row(&self, line: i32) -> Option<Vec<CharCel>> {
+        if line < 0 {
+            return None;
+        }
+        let mut row = self.buffer.get(line as usize)?;
+        for span in self.syntax.spans(line as usize) {
+            for cell in row.iter_mut().take(span.end).skip(span.start) {
+                cell.fg_on = true;
+                cell.fg = span.token.color();
             }
+        }
+        Some(row)
+    }
 
-            data.push(row.get(position.0 as usize).unwrap().clone());
+    /// return the number of rows in the buffer
+    pub fn line_count(&self) -> usize {
+        self.buffer.len()
+    }
 
-            // move to the next character
-            position = position.add(&Vector2(1, 0));
+    /// return the number of characters in the buffer, counting each char of a line break
+    pub fn len_chars(&self) -> usize {
+        self.buffer.len_chars()
+    }
+
+    /// convert a location into a char index into the text, after clamping it
+    pub fn location_to_index(&self, location: Vector2) -> usize {
+        self.buffer.to_index(self.clamp_vector(location))
+    }
+
+    /// convert a char index into the text into a location
+    pub fn index_to_location(&self, index: usize) -> Vector2 {
+        self.buffer.to_location(index)
+    }
+
+    /// return the number of bytes the text takes up as utf-8, as in `to_string`
+    pub fn len_bytes(&self) -> usize {
+        self.buffer.len_bytes()
+    }
+
+    /// convert a location into a byte offset into the text, after clamping it
+    pub fn location_to_byte(&self, location: Vector2) -> usize {
+        self.buffer.char_to_byte(self.location_to_index(location))
+    }
+
+    /// convert a byte offset into the text into a location
+    pub fn byte_to_location(&self, byte: usize) -> Vector2 {
+        self.buffer.to_location(self.buffer.byte_to_char(byte))
+    }
+
+    /// return the start and end of the selection, in order
+    pub fn selection_range(&self) -> Option<(Vector2, Vector2)> {
+        match self.select_start {
+            Some(start) if self.selecting => {
+                let start = self.clamp_vector(start);
+                Some((start.min(self.cursor), start.max(self.cursor)))
+            }
+            _ => None,
         }
+    }
+
+    /// Copy the text at location `from` to location `to`
+    pub fn copy_range<T: Into<Vector2>>(&self, from: T, to: T) -> Vec<CharCel> {
+        use std::cmp::{max, min};
+        let (from, to) = (self.clamp_vector(from.into()), self.clamp_vector(to.into()));
+        let start = min(from, to);
+        let end = max(from, to);
 
-        data
+        self.buffer.slice(start, end).map(CharCel::from).collect()
     }
 
     /// cut the text from location from, to location to
@@ -327,59 +1090,121 @@ impl Editor {
 
         let (from, to) = (self.clamp_vector(from.into()), self.clamp_vector(to.into()));
 
-        let original_cursor = self.cursor.clone();
+        let original_cursor = self.cursor;
 
         let start = min(from, to);
         let end = max(from, to);
 
-        let mut buffer = VecDeque::<CharCel>::new();
-        self.set_cursor(end);
-
-        let mut rows = 0;
-        let mut cols = 0;
-        while self.cursor > start {
-            println!("{:?}, {:?}, {:?}", self.cursor, start, end);
-            if let Some(x) = self.delete() {
-                match x.char {
-                    '\n' => {
-                        rows += 1;
-                        cols = 0;
-                    }
-                    _ => cols += 1,
-                }
-                buffer.push_front(x);
-            } else {
-                break;
-            }
-        }
+        self.begin_edit(EditKind::Other);
+        let text = self.remove_text(start, end);
+        let rows = end.1 - start.1;
 
-        let original_cursor = if original_cursor > start && original_cursor < end {
+        // shift the cursor to account for the removed text
+        let original_cursor = if original_cursor <= start {
+            original_cursor
+        } else if original_cursor <= end {
             start
         } else if original_cursor.1 == end.1 {
-            original_cursor.add(&Vector2(-cols, 0))
-        } else if original_cursor > end {
-            original_cursor.add(&Vector2(0, -rows))
+            Vector2(start.0 + original_cursor.0 - end.0, start.1)
         } else {
-            original_cursor
+            original_cursor.add(Vector2(0, -rows))
         };
 
         // restore the cursor to it's original location after deleting the text
         self.set_cursor(original_cursor);
+        self.end_edit();
+
+        text.chars().map(CharCel::from).collect()
+    }
+
+    /// Replace the text between two locations with `text`, whose line breaks are converted
+    /// to the line ending of the buffer. the cursor is moved to follow the text around it
+    ///
+    /// # Returns
+    /// the location at the end of the inserted text
+    pub fn replace_range(
+        &mut self,
+        from: impl Into<Vector2>,
+        to: impl Into<Vector2>,
+        text: &str,
+    ) -> Vector2 {
+        use std::cmp::{max, min};
+        let (from, to) = (self.clamp_vector(from.into()), self.clamp_vector(to.into()));
+        let (start, end) = (min(from, to), max(from, to));
+
+        self.begin_edit(EditKind::Other);
+        let cursor_index = self.buffer.to_index(self.cursor);
+        let (start_index, end_index) = (self.buffer.to_index(start), self.buffer.to_index(end));
+
+        let text = self.normalize_line_endings(text);
+        self.remove_text(start, end);
+        let inserted = self.insert_text(start, &text);
+        // line breaks before `start` may have changed length, but not its location
+        let text_end = self.buffer.to_index(start) + inserted;
+
+        // text after the replaced range shifts, a cursor inside of it moves to the end
+        self.cursor = if cursor_index <= start_index {
+            self.cursor
+        } else if cursor_index < end_index {
+            self.buffer.to_location(text_end)
+        } else {
+            self.buffer.to_location(text_end + cursor_index - end_index)
+        };
+        self.end_edit();
+
+        self.buffer.to_location(text_end)
+    }
+
+    /// convert every line break in `text` to the line ending of the buffer
+    fn normalize_line_endings(&self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\r' => {
+                    if chars.peek() == Some(&'\n') {
+                        chars.next();
+                    }
+                    out.push_str(self.line_ending.as_str());
+                }
+                '\n' => out.push_str(self.line_ending.as_str()),
+                c => out.push(c),
+            }
+        }
+        out
+    }
 
-        Vec::from(buffer)
+    /// Group every edit until `end_group` into a single undo step
+    pub fn begin_group(&mut self) {
+        self.begin_edit(EditKind::Other);
     }
 
+    /// Finish a group started with `begin_group`
+    pub fn end_group(&mut self) {
+        self.end_edit();
+    }
+
+    /// Write at every cursor, replacing the selected text.
     /// After writing, the cursor location will be moved `content.len()` characters to the right
     pub fn write(&mut self, content: char) {
-        self.write_at(self.cursor.clone(), content);
-        self.move_cursor(Vector2(1, 0));
-
-        if content == '\n' {
-            // if a newline was inserted, move down to the beginning of next line
-            // move the cursor to the beginning of the next line
-            self.cursor.0 = 0;
-            self.set_cursor((0, self.cursor.1 + 1));
+        match self.block {
+            Some(block) if content != '\n' => return self.write_block(block, content),
+            _ => self.block = None,
         }
+        self.begin_edit(EditKind::Typing);
+        self.each_cursor(|editor| {
+            editor.delete_selection();
+            editor.write_at(editor.cursor, content);
+            editor.move_primary(Vector2(1, 0));
+
+            if content == '\n' {
+                // if a newline was inserted, move down to the beginning of next line
+                // move the cursor to the beginning of the next line
+                editor.cursor.0 = 0;
+                editor.set_cursor((0, editor.cursor.1 + 1));
+            }
+        });
+        self.end_edit();
     }
 
     /// Write a group of cells after `location`
@@ -390,40 +1215,14 @@ impl Editor {
     /// the nearest valid position
     pub fn write_at(&mut self, location: impl Into<Vector2>, content: char) {
         let location = self.clamp_vector(location.into());
-        let Vector2(x, y) = location;
 
-        // retrieve or create the row at location `y`
-        // a row should only need to be created when the vector is empty
-        let row = match self.buffer.get_mut(y as usize) {
-            Some(row) => row,
-            None => {
-                self.buffer.push(Vec::new());
-                self.buffer.get_mut(y as usize).unwrap()
-            }
-        };
-        let len = row.len();
-
-        // spawn a new line, moving all characters after it to the back
-        // otherwise insert a character into the line at the cursor position
+        self.begin_edit(EditKind::Other);
         if content == '\n' {
-            if (x as usize) >= len {
-                self.buffer.insert((y + 1) as usize, Vec::new()); // insert an empty line
-            } else {
-                // move the content after the cursor to the next line
-                let (before, after) = match row.split_at(x as usize) {
-                    (before, after) => (before.to_vec(), after.to_vec()),
-                };
-                *row = before;
-                self.buffer.insert((y + 1) as usize, after);
-            }
+            self.insert_text(location, self.line_ending.as_str());
         } else {
-            // append or insert a new character depending on the cursor position
-            if x >= len as i32 {
-                row.push(CharCel::from(content));
-            } else {
-                row.insert(x as usize, CharCel::from(content));
-            }
+            self.insert_text(location, content.encode_utf8(&mut [0; 4]));
         }
+        self.end_edit();
     }
 
     /// Delete the cell under the cursor and then shift the cursor one to the left
@@ -431,61 +1230,314 @@ impl Editor {
     /// # Panics
     /// If `selecting` is true and `select_start` is `none`
     pub fn delete(&mut self) -> Option<CharCel> {
-        // delete the entire selection if a current selection is in progress
-        if self.selecting {
-            self.selecting = false;
-            self.cut_range(self.select_start.unwrap(), self.cursor.clone());
+        if let Some(block) = self.block {
+            self.delete_block(block);
+            return None;
         }
+        self.begin_edit(EditKind::Other);
+
+        // the primary cursor goes first, so the first cell deleted is its own
+        let mut deleted = Vec::new();
+        self.each_cursor(|editor| {
+            // delete the entire selection if a current selection is in progress
+            if editor.delete_selection() {
+                deleted.push(None);
+                return;
+            }
 
-        // store the original length of the previous row to jump to when the line below it is deleted
-        let previous_row_length = if self.cursor.1 > 0 {
-            self.buffer
-                .get(self.cursor.1 as usize - 1)
-                .map(|x| x.len())
-                .unwrap()
-        } else {
-            0
-        };
+            // delete the character before the cursor and move to where it was
+            deleted.push(match editor.delete_before(editor.cursor) {
+                Some((location, cell)) => {
+                    editor.set_cursor(location);
+                    Some(cell)
+                }
+                None => None,
+            });
+        });
+
+        self.end_edit();
+        deleted.into_iter().next().flatten()
+    }
+
+    /// remove the text selected by the primary cursor, returning true if there was any
+    fn delete_selection(&mut self) -> bool {
+        match self.select_start.filter(|_| self.selecting) {
+            Some(start) => {
+                self.selecting = false;
+                self.select_start = None;
+                self.cut_range(start, self.cursor);
+                true
+            }
+            None => false,
+        }
+    }
 
-        // delete the character before the cursor
-        let val = self.delete_at(self.cursor);
+    /// insert `text` at every cursor, replacing the selected text, and move past it.
+    /// when `text` has a line for each cursor, like text copied from them, each cursor gets
+    /// its own line in the order they are in the buffer
+    pub fn paste(&mut self, text: &str) {
+        self.begin_edit(EditKind::Other);
+        if let Some(block) = self.block.take() {
+            // the block is emptied, leaving a cursor on each of its lines
+            self.remove_block(block);
+            self.block = None;
+        }
+        let pieces = self.paste_pieces(text);
+        self.each_cursor(|editor| {
+            let piece = &pieces[editor.cursor_rank().min(pieces.len() - 1)];
+            editor.delete_selection();
+            let location = editor.cursor;
+            let end = editor.replace_range(location, location, piece);
+            editor.set_cursor(end);
+        });
+        self.end_edit();
+    }
+
+    /// replace `pasted`, the text just pasted before every cursor, with `text`.
+    /// returns false without changing anything if the text before a cursor is something else
+    pub fn replace_paste(&mut self, pasted: &str, text: &str) -> bool {
+        let pieces = self.paste_pieces(pasted);
+        // the start of the pasted text before each cursor, in order
+        let mut ends: Vec<Vector2> = self.all_cursors().iter().map(|c| c.location).collect();
+        ends.sort();
+        let mut starts = Vec::new();
+        for (rank, end) in ends.into_iter().enumerate() {
+            let piece = &pieces[rank.min(pieces.len() - 1)];
+            let index = self.location_to_index(end);
+            let len = piece.chars().count();
+            let start = match index.checked_sub(len) {
+                Some(start) => self.index_to_location(start),
+                None => return false,
+            };
+            if !self.copy_range(start, end).iter().map(|c| c.char).eq(piece.chars()) {
+                return false;
+            }
+            starts.push(start);
+        }
 
-        let Vector2(_, y) = self.cursor;
+        self.begin_edit(EditKind::Other);
+        self.each_cursor(|editor| {
+            let start = starts[editor.cursor_rank()];
+            editor.select_range(start, editor.cursor);
+        });
+        self.paste(text);
+        self.end_edit();
+        true
+    }
 
-        // a line has been deleted, move to the previous line
-        if self.cursor.0 == 0 && y >= 1 {
-            self.set_cursor((previous_row_length as i32, y - 1));
+    /// split `text` into what is pasted at each cursor, in the order the cursors are in the
+    /// buffer: a line each when there are as many lines as cursors, or else all of it
+    fn paste_pieces(&self, text: &str) -> Vec<String> {
+        let lines = line_ending::lines(text);
+        let count = self.cursor_count();
+        if count > 1 && lines.len() == count {
+            lines.iter().map(|line| self.normalize_line_endings(line)).collect()
         } else {
-            // otherwise move one character to the left
-            self.move_cursor((-1, 0));
+            vec![self.normalize_line_endings(text)]
         }
+    }
 
-        val
+    /// return the number of cursors before the primary one in the buffer
+    fn cursor_rank(&self) -> usize {
+        self.cursors.iter().filter(|c| c.location < self.cursor).count()
     }
 
     /// Delete the cell at `location` it it exists
     pub fn delete_at(&mut self, location: impl Into<Vector2>) -> Option<CharCel> {
-        let Vector2(x, y) = self.clamp_vector(location.into());
+        self.delete_before(location.into()).map(|(_, cell)| cell)
+    }
+
+    /// Delete the cell before `location`, returning where the deleted cell was located
+    fn delete_before(&mut self, location: Vector2) -> Option<(Vector2, CharCel)> {
+        let Vector2(x, y) = self.clamp_vector(location);
+        let len = self.buffer.row_len(y as usize)? as i32;
+
+        // find the location of the cell before `location`
+        let target = if x == 0 && y >= 1 {
+            // the line break at the end of the previous line
+            Vector2(self.buffer.row_len((y - 1) as usize)? as i32, y - 1)
+        } else if x != 0 && x < len {
+            Vector2(x - 1, y)
+        } else if x != 0 && len != 0 {
+            // if the cursor is in a location greater than the last location in the line
+            // delete the last element in the buffer
+            Vector2(len - 1, y)
+        } else {
+            return None;
+        };
+
+        self.begin_edit(EditKind::Other);
+        let text = self.remove_text(target, self.buffer.next_location(target)?);
+        self.end_edit();
+        // a line break is reported as a single '\n' cell regardless of its style
+        let c = if LineEnding::of(&text).is_some() { '\n' } else { text.chars().next()? };
+        Some((target, CharCel::from(c)))
+    }
+
+    /// Insert `text` at `location` and record it in the history, returning the number of
+    /// chars inserted. line breaks in `text` split the row
+    fn insert_text(&mut self, location: Vector2, text: &str) -> usize {
+        if text.is_empty() {
+            return 0;
+        }
+        self.modified = true;
+        let (cursors, index) = (self.cursor_indices(), self.buffer.to_index(location));
+        let text = self.separate_breaks(index, text);
+        self.buffer_insert(location, &text);
+        let inserted = text.chars().count();
+        self.shift_cursors(cursors, |i| if i >= index { i + inserted } else { i });
+        self.history.record(Change::Insert(location, text));
+        inserted
+    }
+
+    /// return `text` to insert at `index`, with a line break at either end written as CRLF
+    /// if it would otherwise join a lone `\r` before it or a `\n` after it into one break
+    fn separate_breaks(&self, index: usize, text: &str) -> String {
+        let after_cr = index > 0 && self.buffer.char_at(index - 1) == Some('\r');
+        let before_lf = self.buffer.char_at(index) == Some('\n');
+        let mut text = text.to_string();
+        if after_cr && text.starts_with('\n') {
+            text.insert(0, '\r');
+        }
+        if before_lf && text.ends_with('\r') {
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Remove the text between `from` and `to` and record it in the history
+    fn remove_text(&mut self, from: Vector2, to: Vector2) -> String {
+        let (start, end) = (self.buffer.to_index(from), self.buffer.to_index(to));
+        if start > 0
+            && start < end
+            && self.buffer.char_at(start - 1) == Some('\r')
+            && self.buffer.char_at(end) == Some('\n')
+        {
+            // the lone \r before the text would join the \n after it into one break, so it
+            // becomes a CRLF first. this leaves the location of every row as it was
+            let cr = self.buffer.to_location(start - 1);
+            self.remove_text(cr, from);
+            self.insert_text(cr, "\r\n");
+        }
+
+        let (cursors, start) = (self.cursor_indices(), self.buffer.to_index(from));
+        let text = self.buffer_remove(from, to);
+        let removed = text.chars().count();
+        // cursors in the removed text move to where it was
+        self.shift_cursors(cursors, |i| {
+            if i > start {
+                i.max(start + removed) - removed
+            } else {
+                i
+            }
+        });
+        if !text.is_empty() {
+            self.modified = true;
+            self.history.record(Change::Remove(from, text.clone()));
+        }
+        text
+    }
+
+    /// insert into the buffer, keeping the syntax highlighting up to date
+    fn buffer_insert(&mut self, location: Vector2, text: &str) {
+        let rows = self.buffer.len();
+        self.buffer.insert_str(location, text);
+        self.rows_changed(location.y() as usize, rows);
+    }
+
+    /// remove from the buffer, keeping the syntax highlighting up to date
+    fn buffer_remove(&mut self, from: Vector2, to: Vector2) -> String {
+        let rows = self.buffer.len();
+        let text = self.buffer.remove_range(from, to);
+        self.rows_changed(from.y() as usize, rows);
+        text
+    }
+
+    /// re-highlight after row `y` was edited, changing the buffer from `rows_before` rows
+    fn rows_changed(&mut self, y: usize, rows_before: usize) {
+        let rows = self.buffer.len();
+        let removed = 1 + rows_before.saturating_sub(rows);
+        let inserted = 1 + rows.saturating_sub(rows_before);
+        self.syntax.edit(y, removed, inserted);
+        self.syntax.update(&self.buffer);
+    }
+
+    /// the cursor and selection state recorded in the history
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            cursor: self.cursor,
+            select_start: self.select_start,
+            selecting: self.selecting,
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.cursor = self.clamp_vector(snapshot.cursor);
+        self.select_start = snapshot.select_start;
+        self.selecting = snapshot.selecting;
+    }
 
-        if let Some(row) = self.buffer.get_mut(y as usize) {
-            if x == 0 && y >= 1 {
-                let mut x = self.buffer.remove(y as usize);
-                // append the current line to the previous line
-                self.buffer
-                    .get_mut((y - 1) as usize)
-                    .unwrap()
-                    .append(&mut x);
-                return Some(CharCel::from('\n'));
-            } else if x != 0 && (x as usize) < row.len() {
-                return Some(row.remove((x - 1) as usize));
-            } else if x != 0 && row.len() != 0 {
-                // if the cursor is in a location greater than the last location in the line
-                // delete the last element in the buffer
-                return Some(row.remove(row.len() - 1));
+    fn begin_edit(&mut self, kind: EditKind) {
+        let snapshot = self.snapshot();
+        self.history.begin(kind, snapshot);
+    }
+
+    fn end_edit(&mut self) {
+        let snapshot = self.snapshot();
+        self.history.end(snapshot);
+    }
+
+    /// Revert the most recent edit
+    ///
+    /// # Returns
+    /// false if there was nothing to undo
+    pub fn undo(&mut self) -> bool {
+        let transaction = match self.history.undo() {
+            Some(t) => t,
+            None => return false,
+        };
+
+        for change in transaction.changes.iter().rev() {
+            match change {
+                Change::Insert(location, text) => {
+                    let end = self.buffer.offset(*location, text.chars().count());
+                    self.buffer_remove(*location, end);
+                }
+                Change::Remove(location, text) => self.buffer_insert(*location, text),
             }
         }
+        self.modified = true;
+        // the history only keeps the primary cursor
+        self.cursors.clear();
+        self.block = None;
+        self.restore(transaction.before);
+        true
+    }
+
+    /// Reapply the most recently undone edit
+    ///
+    /// # Returns
+    /// false if there was nothing to redo
+    pub fn redo(&mut self) -> bool {
+        let transaction = match self.history.redo() {
+            Some(t) => t,
+            None => return false,
+        };
 
-        None
+        for change in transaction.changes.iter() {
+            match change {
+                Change::Insert(location, text) => self.buffer_insert(*location, text),
+                Change::Remove(location, text) => {
+                    let end = self.buffer.offset(*location, text.chars().count());
+                    self.buffer_remove(*location, end);
+                }
+            }
+        }
+        self.modified = true;
+        self.cursors.clear();
+        self.block = None;
+        self.restore(transaction.after);
+        true
     }
 
     pub fn selection(&self) -> String {
@@ -509,41 +1561,65 @@ impl Editor {
 }
 
 
+/// join pieces of text with line breaks between them
+fn join_lines(pieces: impl Iterator<Item = Vec<CharCel>>) -> Vec<CharCel> {
+    let mut cells = Vec::new();
+    for (i, piece) in pieces.enumerate() {
+        if i > 0 {
+            cells.push(CharCel::from('\n'));
+        }
+        cells.extend(piece);
+    }
+    cells
+}
+
 /// Return the contents of the buffer as a string
 impl std::fmt::Display for Editor {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let char_cells = vec![CharCel::from('b'), CharCel::from('c')];
-        // let char_cells: Vec<Vec<CharCel>> = self
-        //     .buffer
-        //     .iter()
-        //     .map(|x| x.iter().map(|x| x).collect::<Vec<CharCel>>())
-        //     .collect();
-
-        let d: Vec<CharCel> = self.buffer
-            .iter()
-            .flat_map(|x| x )
-            .map(|x| *x)
-            .collect();
+        write!(f, "{}", self.buffer)
+    }
+}
 
-        let s = StringCel::from(d);
+/// Read the file at `path` into a string
+pub fn load(path: &Path) -> io::Result<String> {
+    fs::read_to_string(path)
+}
 
-        let text: String = self
-            .buffer
-            .iter()
-            .map(|x| x.iter().map(|x| x.char).collect::<String>())
-            .collect::<Vec<String>>()
-            .join("\n");
-        write!(f, "{}", s)
+/// Atomically replace the file at `path` with `contents`.
+/// The data is written and synced to a temporary file in the same directory first,
+/// then renamed over the destination.
+pub fn store(path: &Path, contents: &str) -> io::Result<()> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file path"))?;
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(name);
+    tmp_name.push(".rust-ed.tmp");
+    let tmp = path.with_file_name(tmp_name);
+
+    let result = (|| {
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(contents.as_bytes())?;
+        // keep the permissions of the file being replaced
+        if let Ok(metadata) = fs::metadata(path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+        file.sync_all()?;
+        fs::rename(&tmp, path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
     }
+    result
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
     use std::cmp::Ordering;
-    use core::panicking::panic;
 
-    const TEST_STRING: &'static str = include_str!("../resources/sample_text.txt");
+    const TEST_STRING: &str = include_str!("../resources/sample_text.txt");
 
     #[test]
     fn test_vector_cmp() {
@@ -596,11 +1672,11 @@ mod test {
         for row in 0..(300 / 5) {
             for col in 0..(300 / 5) {
                 let (row, col) = ((row * 5), (col * 5));
-                let before_set_cursor = editor.cursor.clone();
+                let before_set_cursor = editor.cursor;
                 editor.set_cursor((col, row));
-                let before_write = editor.cursor.clone();
+                let before_write = editor.cursor;
                 editor.write('\0');
-                let after_write = editor.cursor.clone();
+                let after_write = editor.cursor;
                 if let Some(x) = editor.delete() {
                     if x.char != '\0' {
                         panic!(
@@ -656,7 +1732,7 @@ mod test {
         editor.set_cursor((0, 0));
         editor.write('\n');
 
-        assert_eq!(editor.buffer.len(), 2 as usize);
+        assert_eq!(editor.buffer.len(), 2_usize);
 
         // a new line was successfully inserted at the beginning of the line
         // moving the contents of the previous line to the next
@@ -702,9 +1778,7 @@ mod test {
     }
 
     #[test]
-    // TODO : Consider removing carriage returns within the editor function
-    // make sure the output is equal to the input
-    // to make this work you have to transform \r\n to \n
+    // make sure the output is equal to the input, including carriage returns
     fn test_editor_from_string() {
         let test_cases = vec![
             "please wait warmly",
@@ -718,9 +1792,319 @@ mod test {
         ];
 
         for i in test_cases {
-            let i = i.replace("\r", "");
             let editor = Editor::from(i.to_string());
             assert_eq!(editor.to_string(), i.to_string());
         }
     }
+
+    #[test]
+    fn test_editor_line_endings() {
+        let mut editor = Editor::from("one\r\ntwo\r\nthree\nfour");
+        assert_eq!(editor.line_ending(), LineEnding::CrLf);
+        assert!(editor.line_ending_count().is_mixed());
+        assert_eq!(editor.line_count(), 4);
+        assert_eq!(editor.get_line(1).unwrap(), "two");
+
+        // new lines use the dominant style, existing ones are left alone
+        editor.set_cursor((4, 3));
+        editor.write('\n');
+        assert_eq!(editor.to_string(), "one\r\ntwo\r\nthree\nfour\r\n");
+
+        // a line break is deleted as a whole
+        editor.delete();
+        editor.set_cursor((0, 1));
+        assert_eq!(editor.delete().map(|c| c.char), Some('\n'));
+        assert_eq!(editor.to_string(), "onetwo\r\nthree\nfour");
+
+        editor.set_line_ending(LineEnding::Lf);
+        assert_eq!(editor.to_string(), "onetwo\nthree\nfour");
+        assert!(!editor.line_ending_count().is_mixed());
+
+        editor.undo();
+        assert_eq!(editor.to_string(), "onetwo\r\nthree\nfour");
+
+        editor.set_line_ending(LineEnding::Cr);
+        assert_eq!(editor.to_string(), "onetwo\rthree\rfour");
+        assert_eq!(editor.line_count(), 3);
+
+        // characters taking more than one byte aren't mistaken for line breaks
+        let mut editor = Editor::from("éこ");
+        editor.set_cursor((2, 0));
+        assert_eq!(editor.delete().map(|c| c.char), Some('こ'));
+        assert_eq!(editor.delete().map(|c| c.char), Some('é'));
+        assert_eq!(editor.to_string(), "");
+
+        // a lone \r and a \n next to each other would read as a single CRLF break
+        let mut editor = Editor::from("a\rb\nc\nd");
+        editor.set_cursor((0, 1));
+        editor.write('\n');
+        assert_eq!(editor.line_count(), 5);
+        assert_eq!(editor.cursor_pos(), Vector2(0, 2));
+        assert_eq!(editor.get_line(2).unwrap(), "b");
+        editor.undo();
+        assert_eq!(editor.to_string(), "a\rb\nc\nd");
+
+        let mut editor = Editor::from("a\rx\nc");
+        editor.set_cursor((1, 1));
+        editor.delete();
+        assert_eq!(editor.line_count(), 3);
+        assert_eq!(editor.to_string(), "a\r\n\nc");
+        assert_eq!(editor.get_line(2).unwrap(), "c");
+        editor.undo();
+        assert_eq!(editor.to_string(), "a\rx\nc");
+
+        let mut editor = Editor::from("a\r\rb\n\nc");
+        editor.set_line_ending(LineEnding::Lf);
+        assert_eq!(editor.to_string(), "a\n\nb\n\nc");
+        editor.set_line_ending(LineEnding::Cr);
+        assert_eq!(editor.to_string(), "a\r\rb\r\rc");
+    }
+
+    #[test]
+    fn test_editor_open_save() {
+        let dir = std::env::temp_dir().join(format!("rust-ed-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("open_save.txt");
+        let _ = fs::remove_file(&path);
+
+        // opening a missing file yields an empty buffer bound to that path
+        let mut editor = Editor::open(&path).unwrap();
+        assert_eq!(editor.to_string(), "");
+        assert_eq!(editor.path(), Some(path.as_path()));
+
+        for c in "hello\nworld\n".chars() {
+            editor.write(c);
+        }
+        assert!(editor.is_modified());
+        editor.save().unwrap();
+        assert!(!editor.is_modified());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello\nworld\n");
+
+        let editor = Editor::open(&path).unwrap();
+        assert_eq!(editor.to_string(), "hello\nworld\n");
+
+        // no temporary file is left behind
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+
+        // saving without a path is an error rather than a panic
+        assert!(Editor::new().save().is_err());
+    }
+
+    #[test]
+    fn test_editor_undo_redo() {
+        let mut editor = Editor::from("hello world");
+
+        // consecutive typing is undone as a single step
+        editor.set_cursor((5, 0));
+        for c in ", dear".chars() {
+            editor.write(c);
+        }
+        assert_eq!(editor.to_string(), "hello, dear world");
+
+        // a line break starts a new step
+        editor.write('\n');
+        assert_eq!(editor.to_string(), "hello, dear\n world");
+
+        assert!(editor.undo());
+        assert_eq!(editor.to_string(), "hello, dear world");
+        assert_eq!(editor.cursor, Vector2(11, 0));
+
+        assert!(editor.undo());
+        assert_eq!(editor.to_string(), "hello world");
+        assert_eq!(editor.cursor, Vector2(5, 0));
+        assert!(!editor.undo());
+
+        assert!(editor.redo());
+        assert_eq!(editor.to_string(), "hello, dear world");
+        assert!(editor.redo());
+        assert_eq!(editor.to_string(), "hello, dear\n world");
+        assert!(!editor.redo());
+
+        // deleting a selection restores the text and the selection
+        editor.set_cursor((0, 0));
+        editor.begin_select();
+        editor.set_cursor((3, 1));
+        editor.delete();
+        assert_eq!(editor.to_string(), "rld");
+
+        assert!(editor.undo());
+        assert_eq!(editor.to_string(), "hello, dear\n world");
+        assert_eq!(editor.cursor, Vector2(3, 1));
+        assert!(editor.is_selecting());
+        assert_eq!(editor.selection(), "hello, dear\n wo");
+
+        // a new edit discards the redo stack
+        editor.clear_selection();
+        editor.write('!');
+        assert!(!editor.redo());
+    }
+
+    #[test]
+    fn test_editor_multiple_cursors() {
+        let mut editor = Editor::from("one\ntwo\nthree");
+        assert!(editor.add_cursor_vertically(1));
+        assert!(editor.add_cursor_vertically(1));
+        assert!(!editor.add_cursor_vertically(1));
+        assert_eq!(editor.cursor_count(), 3);
+
+        editor.write('>');
+        editor.move_cursor((9999, 0));
+        editor.write('!');
+        assert_eq!(editor.to_string(), ">one!\n>two!\n>three!");
+        editor.delete();
+        editor.delete();
+        assert_eq!(editor.to_string(), ">on\n>tw\n>thre");
+
+        // cursors moved onto the same place are merged
+        editor.move_cursor((0, -5));
+        assert_eq!(editor.cursor_count(), 1);
+        assert_eq!(editor.cursor_pos(), Vector2(3, 0));
+
+        let mut editor = Editor::from("let a = a + a;");
+        editor.set_cursor((4, 0));
+        editor.begin_select();
+        editor.set_cursor((5, 0));
+        assert!(editor.add_cursor_at_next_match());
+        assert!(editor.add_cursor_at_next_match());
+        assert!(!editor.add_cursor_at_next_match());
+        assert_eq!(editor.cursor_pos(), Vector2(13, 0));
+
+        let copied: String = editor.copy().unwrap().into_iter().map(|c| c.char).collect();
+        assert_eq!(copied, "a\na\na");
+        editor.cut();
+        editor.write('b');
+        assert_eq!(editor.to_string(), "let b = b + b;");
+
+        // the selections of the other cursors are copied and cut without one at the primary
+        let mut editor = Editor::from("one two");
+        editor.select_range((0, 0), (3, 0));
+        editor.add_cursor((7, 0));
+        let copied: String = editor.copy().unwrap().into_iter().map(|c| c.char).collect();
+        assert_eq!(copied, "one");
+        assert!(editor.cut().is_some());
+        assert_eq!(editor.to_string(), " two");
+        assert!(editor.copy().is_none());
+    }
+
+    #[test]
+    fn test_editor_block_selection() {
+        let text = |cells: Vec<CharCel>| cells.into_iter().map(|c| c.char).collect::<String>();
+        let mut editor = Editor::from("abcdef\n\nabcdef");
+        editor.set_cursor((1, 0));
+        editor.extend_block((4, 2));
+        assert_eq!(editor.cursor_count(), 3);
+
+        // short lines are padded to the width of the block
+        assert_eq!(text(editor.copy().unwrap()), "bcd\n   \nbcd");
+        assert_eq!(text(editor.cut().unwrap()), "bcd\n   \nbcd");
+        assert_eq!(editor.to_string(), "aef\n\naef");
+
+        // typing into the empty block writes on every line
+        editor.write('x');
+        assert_eq!(editor.to_string(), "axef\n x\naxef");
+        assert_eq!(editor.block().unwrap().columns(), (2, 2));
+        editor.delete();
+        assert_eq!(editor.to_string(), "aef\n \naef");
+
+        assert!(editor.undo());
+        assert_eq!(editor.to_string(), "axef\n x\naxef");
+        assert_eq!(editor.block(), None);
+
+        // deleting a wide character moves the block by its width
+        let mut editor = Editor::from("こab\nこab");
+        editor.set_cursor((1, 0));
+        editor.extend_block((2, 1));
+        editor.delete();
+        assert_eq!(editor.to_string(), "ab\nab");
+        assert_eq!(editor.block().unwrap().columns(), (0, 0));
+        editor.write('x');
+        assert_eq!(editor.to_string(), "xab\nxab");
+    }
+
+    #[test]
+    fn test_editor_word_selection() {
+        let mut editor = Editor::from("let x_1 = a.b;\n  end");
+        assert_eq!(editor.word_at((5, 0)), (Vector2(4, 0), Vector2(7, 0)));
+        assert_eq!(editor.word_at((11, 0)), (Vector2(11, 0), Vector2(12, 0)));
+        assert_eq!(editor.word_at((99, 0)), (Vector2(13, 0), Vector2(14, 0)));
+
+        assert_eq!(editor.word_boundary((3, 0), 1), Vector2(7, 0));
+        assert_eq!(editor.word_boundary((14, 0), 1), Vector2(0, 1));
+        assert_eq!(editor.word_boundary((5, 1), 1), Vector2(5, 1));
+        assert_eq!(editor.word_boundary((9, 0), -1), Vector2(8, 0));
+        assert_eq!(editor.word_boundary((0, 1), -1), Vector2(14, 0));
+        assert_eq!(editor.word_boundary((0, 0), -1), Vector2(0, 0));
+
+        assert_eq!(editor.line_range(0), (Vector2(0, 0), Vector2(0, 1)));
+        assert_eq!(editor.line_range(1), (Vector2(0, 1), Vector2(5, 1)));
+        editor.select_range((4, 0), (7, 0));
+        assert_eq!(editor.selection_range(), Some((Vector2(4, 0), Vector2(7, 0))));
+        assert_eq!(editor.cursor_pos(), Vector2(7, 0));
+    }
+
+    #[test]
+    fn test_editor_paste() {
+        let mut editor = Editor::from("one two\r\nthree");
+        editor.select_range((4, 0), (7, 0));
+        editor.write('x');
+        assert_eq!(editor.to_string(), "one x\r\nthree");
+
+        // line breaks take the style of the buffer
+        editor.select_range((0, 0), (3, 0));
+        editor.paste("a\nb");
+        assert_eq!(editor.to_string(), "a\r\nb x\r\nthree");
+        assert_eq!(editor.cursor_pos(), Vector2(1, 1));
+        assert!(editor.undo());
+        assert_eq!(editor.to_string(), "one x\r\nthree");
+
+        // backspace only removes the selection
+        editor.select_range((0, 1), (5, 1));
+        editor.delete();
+        assert_eq!(editor.to_string(), "one x\r\n");
+
+        // a line for each cursor is split between them
+        let mut editor = Editor::from("1\n2\n3");
+        editor.add_cursor_vertically(1);
+        editor.add_cursor_vertically(1);
+        editor.paste("a\nb\nc");
+        assert_eq!(editor.to_string(), "a1\nb2\nc3");
+        editor.paste("-");
+        assert_eq!(editor.to_string(), "a-1\nb-2\nc-3");
+
+        // a paste can be swapped for other text, as long as it is still there
+        assert!(editor.replace_paste("-", "<>"));
+        assert_eq!(editor.to_string(), "a<>1\nb<>2\nc<>3");
+        assert!(!editor.replace_paste("-", "+"));
+        editor.set_cursor((0, 0));
+        editor.clear_cursors();
+        editor.paste("x\ny");
+        assert!(editor.replace_paste("x\ny", "z"));
+        assert_eq!(editor.to_string(), "za<>1\nb<>2\nc<>3");
+
+        // lines split by a lone CR are split between the cursors too
+        let mut editor = Editor::from("1\n2\n3");
+        editor.add_cursor_vertically(1);
+        editor.add_cursor_vertically(1);
+        editor.paste("a\rb\r\nc");
+        assert_eq!(editor.to_string(), "a1\nb2\nc3");
+    }
+
+    #[test]
+    fn test_editor_syntax() {
+        use crate::syntax::Token;
+
+        let mut editor = Editor::from("let a = 1;\nlet b = 2;");
+        assert!(!editor.get_cell((0, 0)).unwrap().fg_on);
+        editor.set_language(Some(Language::Rust));
+        assert_eq!(editor.get_cell((0, 1)).unwrap().fg, Token::Keyword.color());
+
+        // opening a block comment recolors the following rows, undoing it restores them
+        editor.set_cursor((0, 0));
+        editor.write('/');
+        editor.write('*');
+        assert_eq!(editor.get_cell((0, 1)).unwrap().fg, Token::Comment.color());
+        assert!(editor.undo());
+        assert_eq!(editor.get_row(1).unwrap()[0].fg, Token::Keyword.color());
+    }
 }
@@ -0,0 +1,266 @@
+//! text storage for the editor.
+//! the text is held in a rope so inserting and deleting stays fast on very large files,
+//! while still being addressed as a grid of (col, row) cells.
+
+use crate::editor::{CharCel, Vector2};
+use crate::line_ending::{LineEnding, LineEndingCount};
+use ropey::Rope;
+
+/// A grid of characters backed by a rope.
+///
+/// Rows are separated by line breaks (`\n`, `\r\n` or `\r`), which are not part of any row
+/// but are kept in the text as they were inserted.
+/// A grid always contains at least one, possibly empty, row.
+#[derive(Clone, Default)]
+pub struct Grid {
+    text: Rope,
+}
+
+impl From<&str> for Grid {
+    fn from(s: &str) -> Self {
+        Self {
+            text: Rope::from_str(s),
+        }
+    }
+}
+
+impl Grid {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// return the number of rows in the grid
+    pub fn len(&self) -> usize {
+        self.text.len_lines()
+    }
+
+    /// return true if the grid contains no characters
+    pub fn is_empty(&self) -> bool {
+        self.text.len_chars() == 0
+    }
+
+    /// return the total number of characters, including line breaks
+    pub fn len_chars(&self) -> usize {
+        self.text.len_chars()
+    }
+
+    /// return the number of characters in row `y`, excluding the line break
+    pub fn row_len(&self, y: usize) -> Option<usize> {
+        let len = self.text.get_line(y)?.len_chars();
+        Some(len - self.line_ending(y).map_or(0, |e| e.as_str().len()))
+    }
+
+    /// return the line break at the end of row `y`, the last row has none
+    pub fn line_ending(&self, y: usize) -> Option<LineEnding> {
+        let line = self.text.get_line(y)?;
+        let len = line.len_chars();
+        match (len.checked_sub(2).map(|i| line.char(i)), len.checked_sub(1).map(|i| line.char(i))) {
+            (Some('\r'), Some('\n')) => Some(LineEnding::CrLf),
+            (_, Some('\n')) => Some(LineEnding::Lf),
+            (_, Some('\r')) => Some(LineEnding::Cr),
+            _ => None,
+        }
+    }
+
+    /// count the line breaks of each style
+    pub fn line_ending_count(&self) -> LineEndingCount {
+        LineEndingCount::of(self.text.chars())
+    }
+
+    /// return a copy of the cells in row `y`
+    pub fn get(&self, y: usize) -> Option<Vec<CharCel>> {
+        let len = self.row_len(y)?;
+        Some(
+            self.text
+                .line(y)
+                .chars()
+                .take(len)
+                .map(CharCel::from)
+                .collect(),
+        )
+    }
+
+    /// return the text of row `y`, excluding the line break
+    pub fn row_text(&self, y: usize) -> Option<String> {
+        let len = self.row_len(y)?;
+        Some(self.text.line(y).slice(..len).to_string())
+    }
+
+    /// return true if row `y` only contains ascii characters,
+    /// in which case every char is its own grapheme cluster
+    pub fn row_is_ascii(&self, y: usize) -> bool {
+        y >= self.len() || {
+            let line = self.text.line(y);
+            line.len_bytes() == line.len_chars()
+        }
+    }
+
+    /// return the cell at `location` if it is within a row
+    pub fn cell(&self, location: Vector2) -> Option<CharCel> {
+        let Vector2(x, y) = location;
+        if x < 0 || y < 0 || x as usize >= self.row_len(y as usize)? {
+            return None;
+        }
+        Some(CharCel::from(self.text.line(y as usize).char(x as usize)))
+    }
+
+    /// convert a grid location into an index into the text.
+    /// the location must be a valid position, where the column may be equal to the row length
+    pub fn to_index(&self, location: Vector2) -> usize {
+        self.text.line_to_char(location.y() as usize) + location.x() as usize
+    }
+
+    /// convert an index into the text into a grid location
+    pub fn to_location(&self, index: usize) -> Vector2 {
+        let index = index.min(self.text.len_chars());
+        let y = self.text.char_to_line(index);
+        let x = index - self.text.line_to_char(y);
+        Vector2(x as i32, y as i32)
+    }
+
+    /// insert a character at `location`.
+    /// a `'\n'` splits the row in two
+    ///
+    /// # Panics
+    /// If the location is not a valid position in the grid
+    pub fn insert(&mut self, location: Vector2, c: char) {
+        let index = self.to_index(location);
+        self.text.insert_char(index, c);
+    }
+
+    /// insert a string at `location`
+    pub fn insert_str(&mut self, location: Vector2, s: &str) {
+        let index = self.to_index(location);
+        self.text.insert(index, s);
+    }
+
+    /// return the location after the character at `location`.
+    /// at the end of a row this is the start of the next row, skipping the whole line break
+    pub fn next_location(&self, location: Vector2) -> Option<Vector2> {
+        let Vector2(x, y) = location;
+        let len = self.row_len(y as usize)? as i32;
+        if x < len {
+            Some(Vector2(x + 1, y))
+        } else if (y as usize) + 1 < self.len() {
+            Some(Vector2(0, y + 1))
+        } else {
+            None
+        }
+    }
+
+    /// remove the character at `location`.
+    /// removing at the end of a row joins it with the next one and returns the line break
+    pub fn remove(&mut self, location: Vector2) -> Option<String> {
+        let next = self.next_location(location)?;
+        Some(self.remove_range(location, next))
+    }
+
+    /// return the characters between two locations
+    pub fn slice(&self, from: Vector2, to: Vector2) -> impl Iterator<Item = char> + '_ {
+        let (from, to) = (self.to_index(from), self.to_index(to));
+        self.text.slice(from..to).chars()
+    }
+
+    /// remove the characters between two locations, returning the removed text
+    pub fn remove_range(&mut self, from: Vector2, to: Vector2) -> String {
+        let (from, to) = (self.to_index(from), self.to_index(to));
+        let removed = self.text.slice(from..to).to_string();
+        self.text.remove(from..to);
+        removed
+    }
+
+    /// return the number of bytes in the text as utf-8
+    pub fn len_bytes(&self) -> usize {
+        self.text.len_bytes()
+    }
+
+    /// convert a char index into a byte offset into the text
+    pub fn char_to_byte(&self, index: usize) -> usize {
+        self.text.char_to_byte(index.min(self.text.len_chars()))
+    }
+
+    /// convert a byte offset into the text into a char index
+    pub fn byte_to_char(&self, byte: usize) -> usize {
+        self.text.byte_to_char(byte.min(self.text.len_bytes()))
+    }
+
+    /// return the character at `index` into the text
+    pub fn char_at(&self, index: usize) -> Option<char> {
+        self.text.get_char(index)
+    }
+
+    /// return the location `count` characters after `location`
+    pub fn offset(&self, location: Vector2, count: usize) -> Vector2 {
+        self.to_location(self.to_index(location) + count)
+    }
+
+    /// iterate over every character in the grid
+    pub fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.text.chars()
+    }
+}
+
+impl std::fmt::Display for Grid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for chunk in self.text.chunks() {
+            f.write_str(chunk)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_grid_rows() {
+        let grid = Grid::from("hello\n\nworld\n");
+        assert_eq!(grid.len(), 4);
+        assert_eq!(grid.row_len(0), Some(5));
+        assert_eq!(grid.row_len(1), Some(0));
+        assert_eq!(grid.row_len(3), Some(0));
+        assert_eq!(grid.row_len(4), None);
+        assert_eq!(grid.cell(Vector2(0, 2)).map(|c| c.char), Some('w'));
+        assert!(grid.cell(Vector2(5, 0)).is_none());
+
+        // every style of line break separates rows
+        let grid = Grid::from("a\r\nb\rc\n");
+        assert_eq!(grid.len(), 4);
+        assert_eq!(grid.row_len(0), Some(1));
+        assert_eq!(grid.row_len(1), Some(1));
+        assert_eq!(grid.line_ending(0), Some(LineEnding::CrLf));
+        assert_eq!(grid.line_ending(1), Some(LineEnding::Cr));
+        assert_eq!(grid.line_ending(2), Some(LineEnding::Lf));
+        assert_eq!(grid.line_ending(3), None);
+
+        assert_eq!(Grid::new().len(), 1);
+    }
+
+    #[test]
+    fn test_grid_edit() {
+        let mut grid = Grid::from("hello world");
+        grid.insert(Vector2(5, 0), '\n');
+        assert_eq!(grid.to_string(), "hello\n world");
+        assert_eq!(grid.to_location(7), Vector2(1, 1));
+        assert_eq!(grid.to_index(Vector2(1, 1)), 7);
+
+        assert_eq!(grid.remove(Vector2(5, 0)).as_deref(), Some("\n"));
+        assert_eq!(grid.remove(Vector2(11, 0)), None);
+        assert_eq!(grid.remove(Vector2(0, 0)).as_deref(), Some("h"));
+        assert_eq!(grid.to_string(), "ello world");
+
+        let text: String = grid.slice(Vector2(1, 0), Vector2(4, 0)).collect();
+        assert_eq!(text, "llo");
+
+        grid.insert_str(Vector2(4, 0), "\nnew\n");
+        assert_eq!(grid.to_string(), "ello\nnew\n world");
+        assert_eq!(grid.offset(Vector2(4, 0), 5), Vector2(0, 2));
+        assert_eq!(grid.remove_range(Vector2(4, 0), Vector2(0, 2)), "\nnew\n");
+        assert_eq!(grid.to_string(), "ello world");
+
+        let mut grid = Grid::from("a\r\nb");
+        assert_eq!(grid.remove(Vector2(1, 0)).as_deref(), Some("\r\n"));
+        assert_eq!(grid.to_string(), "ab");
+    }
+}
@@ -0,0 +1,232 @@
+//! undo and redo history for the editor.
+//! every mutation of the buffer is recorded as a list of primitive changes, grouped into
+//! transactions which can be reverted or reapplied as a single step.
+
+use crate::editor::Vector2;
+
+/// A single primitive modification of the buffer
+#[derive(Clone)]
+pub enum Change {
+    /// text was inserted at the location
+    Insert(Vector2, String),
+    /// text was removed from the location
+    Remove(Vector2, String),
+}
+
+/// Cursor and selection state to restore when a transaction is undone or redone
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    pub cursor: Vector2,
+    pub select_start: Option<Vector2>,
+    pub selecting: bool,
+}
+
+/// Describes the operation that produced a transaction, used to decide when to merge steps
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum EditKind {
+    /// a single character typed at the cursor
+    Typing,
+    /// any other edit
+    Other,
+}
+
+/// A group of changes which is undone and redone as one step
+#[derive(Clone)]
+pub struct Transaction {
+    pub kind: EditKind,
+    pub changes: Vec<Change>,
+    pub before: Snapshot,
+    pub after: Snapshot,
+}
+
+impl Transaction {
+    // typing merges into the previous step as long as the cursor has not moved in between
+    // and the step does not contain a line break
+    fn can_merge(&self, next: &Transaction) -> bool {
+        let is_newline = |t: &Transaction| {
+            t.changes
+                .iter()
+                .any(|c| matches!(c, Change::Insert(_, text) if text.contains('\n')))
+        };
+
+        self.kind == EditKind::Typing
+            && next.kind == EditKind::Typing
+            && self.after == next.before
+            && !is_newline(self)
+            && !is_newline(next)
+    }
+}
+
+/// Stack of undoable and redoable transactions
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Transaction>,
+    redo: Vec<Transaction>,
+    // transaction currently being recorded
+    current: Option<Transaction>,
+    // nesting level of begin calls, the transaction is committed when it returns to zero
+    depth: usize,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// start recording a transaction.
+    /// nested calls are folded into the outermost transaction
+    pub fn begin(&mut self, kind: EditKind, before: Snapshot) {
+        if self.depth == 0 {
+            self.current = Some(Transaction {
+                kind,
+                changes: Vec::new(),
+                before,
+                after: before,
+            });
+        }
+        self.depth += 1;
+    }
+
+    /// finish recording a transaction and push it onto the undo stack.
+    /// transactions without any changes are discarded
+    pub fn end(&mut self, after: Snapshot) {
+        if self.depth == 0 {
+            return;
+        }
+        self.depth -= 1;
+        if self.depth > 0 {
+            return;
+        }
+
+        let mut transaction = match self.current.take() {
+            Some(t) if !t.changes.is_empty() => t,
+            _ => return,
+        };
+        transaction.after = after;
+        self.redo.clear();
+
+        match self.undo.last_mut() {
+            Some(last) if last.can_merge(&transaction) => {
+                last.changes.append(&mut transaction.changes);
+                last.after = transaction.after;
+            }
+            _ => self.undo.push(transaction),
+        }
+    }
+
+    /// record a change into the transaction in progress
+    pub fn record(&mut self, change: Change) {
+        if let Some(current) = self.current.as_mut() {
+            current.changes.push(change);
+        }
+    }
+
+    /// pop the most recent transaction to be reverted
+    pub fn undo(&mut self) -> Option<Transaction> {
+        let transaction = self.undo.pop()?;
+        self.redo.push(transaction.clone());
+        Some(transaction)
+    }
+
+    /// pop the most recently undone transaction to be reapplied
+    pub fn redo(&mut self) -> Option<Transaction> {
+        let transaction = self.redo.pop()?;
+        self.undo.push(transaction.clone());
+        Some(transaction)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// forget all recorded transactions
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn at(x: i32) -> Snapshot {
+        Snapshot {
+            cursor: Vector2(x, 0),
+            select_start: None,
+            selecting: false,
+        }
+    }
+
+    /// record typing `text` at column `x` as one step
+    fn type_at(history: &mut History, x: i32, text: &str) {
+        history.begin(EditKind::Typing, at(x));
+        history.record(Change::Insert(Vector2(x, 0), text.to_string()));
+        history.end(at(x + 1));
+    }
+
+    #[test]
+    fn test_merge_typing() {
+        // characters typed one after another are undone together
+        let mut history = History::new();
+        type_at(&mut history, 0, "a");
+        type_at(&mut history, 1, "b");
+        type_at(&mut history, 2, "c");
+        let typed = history.undo().unwrap();
+        assert_eq!((typed.changes.len(), typed.before, typed.after), (3, at(0), at(3)));
+        assert!(!history.can_undo());
+
+        // moving the cursor or a line break ends the run
+        let mut history = History::new();
+        type_at(&mut history, 0, "a");
+        type_at(&mut history, 5, "b");
+        type_at(&mut history, 6, "\n");
+        type_at(&mut history, 7, "c");
+        let lengths: Vec<usize> =
+            std::iter::from_fn(|| history.undo()).map(|t| t.changes.len()).collect();
+        assert_eq!(lengths, vec![1, 1, 1, 1]);
+
+        // other edits are never merged
+        let mut history = History::new();
+        for x in 0..2 {
+            history.begin(EditKind::Other, at(x));
+            history.record(Change::Remove(Vector2(x, 0), "a".to_string()));
+            history.end(at(x + 1));
+        }
+        assert!(history.undo().is_some());
+        assert!(history.can_undo());
+    }
+
+    #[test]
+    fn test_groups_and_redo() {
+        // nested groups are committed as one step when the outermost ends
+        let mut history = History::new();
+        history.begin(EditKind::Other, at(0));
+        type_at(&mut history, 0, "a");
+        history.begin(EditKind::Other, at(1));
+        history.record(Change::Remove(Vector2(0, 0), "a".to_string()));
+        history.end(at(0));
+        assert!(!history.can_undo());
+        history.end(at(0));
+        let group = history.undo().unwrap();
+        assert_eq!((group.changes.len(), group.before, group.after), (2, at(0), at(0)));
+
+        // an empty group leaves no step, and ending without beginning does nothing
+        history.begin(EditKind::Other, at(0));
+        history.end(at(0));
+        history.end(at(0));
+        assert!(!history.can_undo());
+
+        // a new edit forgets what was undone
+        assert!(history.redo().is_some());
+        assert!(history.undo().is_some());
+        assert!(history.can_redo());
+        type_at(&mut history, 0, "b");
+        assert!(!history.can_redo());
+        assert!(history.redo().is_none());
+    }
+}
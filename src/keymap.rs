@@ -0,0 +1,739 @@
+//! named editor commands and the keys bound to them.
+//! keys are written like `ctrl+s`, `alt+shift+left` or `f1`. the default bindings are written
+//! the same way, and the help screen is generated from whichever bindings are active.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::HashMap;
+use std::fmt;
+
+/// An action which can be bound to a key
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Command {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    LineStart,
+    LineEnd,
+    SelectLeft,
+    SelectRight,
+    SelectUp,
+    SelectDown,
+    SelectLineStart,
+    SelectLineEnd,
+    SelectWordLeft,
+    SelectWordRight,
+    SelectLinesUp,
+    SelectLinesDown,
+    BlockLeft,
+    BlockRight,
+    BlockUp,
+    BlockDown,
+    AddCursorAbove,
+    AddCursorBelow,
+    AddNextMatch,
+    SingleCursor,
+    ScrollUp,
+    ScrollDown,
+    ScrollLeft,
+    ScrollRight,
+    CursorToMiddle,
+    CenterView,
+    Newline,
+    DeleteBackward,
+    Undo,
+    Redo,
+    Copy,
+    Cut,
+    Paste,
+    PasteOlder,
+    CopyToRegister,
+    PasteFromRegister,
+    Search,
+    Replace,
+    Save,
+    NextBuffer,
+    PreviousBuffer,
+    ListBuffers,
+    CloseBuffer,
+    SplitHorizontal,
+    SplitVertical,
+    ClosePane,
+    FocusLeft,
+    FocusRight,
+    FocusUp,
+    FocusDown,
+    GrowLeft,
+    GrowRight,
+    GrowUp,
+    GrowDown,
+    CycleLineNumbers,
+    ToggleWrap,
+    ToggleVi,
+    CycleLineEndings,
+    CommandLine,
+    RecordMacro,
+    PlayMacro,
+    SaveMacros,
+    Refresh,
+    Help,
+    Exit,
+}
+
+/// every command with its name and a description for the help screen, in the order they are
+/// listed there
+const COMMANDS: &[(Command, &str, &str)] = &[
+    (Command::Help, "help", "display this help text"),
+    (Command::MoveUp, "move-up", "move the cursor up"),
+    (Command::MoveDown, "move-down", "move the cursor down"),
+    (Command::MoveLeft, "move-left", "move the cursor left"),
+    (Command::MoveRight, "move-right", "move the cursor right"),
+    (
+        Command::LineStart,
+        "line-start",
+        "move the cursor to the beginning of the line",
+    ),
+    (
+        Command::LineEnd,
+        "line-end",
+        "move the cursor to the end of the line",
+    ),
+    (
+        Command::SelectLeft,
+        "select-left",
+        "extend the selection left",
+    ),
+    (
+        Command::SelectRight,
+        "select-right",
+        "extend the selection right",
+    ),
+    (Command::SelectUp, "select-up", "extend the selection up"),
+    (Command::SelectDown, "select-down", "extend the selection down"),
+    (
+        Command::SelectLineStart,
+        "select-line-start",
+        "extend the selection to the beginning of the line",
+    ),
+    (
+        Command::SelectLineEnd,
+        "select-line-end",
+        "extend the selection to the end of the line",
+    ),
+    (
+        Command::SelectWordLeft,
+        "select-word-left",
+        "extend the selection to the start of the word before",
+    ),
+    (
+        Command::SelectWordRight,
+        "select-word-right",
+        "extend the selection to the end of the word after",
+    ),
+    (
+        Command::SelectLinesUp,
+        "select-lines-up",
+        "extend the selection by a whole line up",
+    ),
+    (
+        Command::SelectLinesDown,
+        "select-lines-down",
+        "extend the selection by a whole line down",
+    ),
+    (
+        Command::BlockLeft,
+        "block-left",
+        "extend the block selection left",
+    ),
+    (
+        Command::BlockRight,
+        "block-right",
+        "extend the block selection right",
+    ),
+    (
+        Command::BlockUp,
+        "block-up",
+        "extend the block selection up",
+    ),
+    (
+        Command::BlockDown,
+        "block-down",
+        "extend the block selection down",
+    ),
+    (
+        Command::AddCursorAbove,
+        "add-cursor-above",
+        "add a cursor on the line above",
+    ),
+    (
+        Command::AddCursorBelow,
+        "add-cursor-below",
+        "add a cursor on the line below",
+    ),
+    (
+        Command::AddNextMatch,
+        "add-next-match",
+        "select the next occurrence of the selection with another cursor",
+    ),
+    (
+        Command::SingleCursor,
+        "single-cursor",
+        "remove every cursor but the newest",
+    ),
+    (Command::ScrollUp, "scroll-up", "move the screen up"),
+    (Command::ScrollDown, "scroll-down", "move the screen down"),
+    (Command::ScrollLeft, "scroll-left", "move the screen left"),
+    (
+        Command::ScrollRight,
+        "scroll-right",
+        "move the screen right",
+    ),
+    (
+        Command::CursorToMiddle,
+        "cursor-to-middle",
+        "move the cursor to the middle of the screen",
+    ),
+    (
+        Command::CenterView,
+        "center-view",
+        "center the screen around the cursor location",
+    ),
+    (Command::Newline, "newline", "break the line at the cursor"),
+    (
+        Command::DeleteBackward,
+        "delete-backward",
+        "delete the character before the cursor",
+    ),
+    (Command::Undo, "undo", "undo the last edit"),
+    (Command::Redo, "redo", "redo the last undone edit"),
+    (Command::Copy, "copy", "copy the selection"),
+    (Command::Cut, "cut", "cut the selection"),
+    (Command::Paste, "paste", "paste from the clipboard"),
+    (
+        Command::PasteOlder,
+        "paste-older",
+        "swap the text just pasted for the copy before it",
+    ),
+    (
+        Command::CopyToRegister,
+        "copy-to-register",
+        "copy the selection to a register named by the next key",
+    ),
+    (
+        Command::PasteFromRegister,
+        "paste-from-register",
+        "paste from a register named by the next key",
+    ),
+    (
+        Command::Search,
+        "search",
+        "search, ^N|down and ^P|up move between matches,\n\
+         enter accepts and esc returns to where the search started",
+    ),
+    (
+        Command::Replace,
+        "replace",
+        "find and replace a regular expression in the file or the selection,\n\
+         $1 in the replacement inserts a capture group",
+    ),
+    (Command::Save, "save", "save the file"),
+    (
+        Command::NextBuffer,
+        "next-buffer",
+        "switch to the next buffer",
+    ),
+    (
+        Command::PreviousBuffer,
+        "previous-buffer",
+        "switch to the previous buffer",
+    ),
+    (
+        Command::ListBuffers,
+        "list-buffers",
+        "list the open buffers",
+    ),
+    (
+        Command::CloseBuffer,
+        "close-buffer",
+        "close the buffer, press twice to discard unsaved changes",
+    ),
+    (
+        Command::SplitHorizontal,
+        "split-horizontal",
+        "split the pane above and below",
+    ),
+    (
+        Command::SplitVertical,
+        "split-vertical",
+        "split the pane side by side",
+    ),
+    (Command::ClosePane, "close-pane", "close the pane"),
+    (
+        Command::FocusLeft,
+        "focus-left",
+        "move the focus to the pane on the left",
+    ),
+    (
+        Command::FocusRight,
+        "focus-right",
+        "move the focus to the pane on the right",
+    ),
+    (
+        Command::FocusUp,
+        "focus-up",
+        "move the focus to the pane above",
+    ),
+    (
+        Command::FocusDown,
+        "focus-down",
+        "move the focus to the pane below",
+    ),
+    (
+        Command::GrowLeft,
+        "grow-left",
+        "move the pane's left or right edge left",
+    ),
+    (
+        Command::GrowRight,
+        "grow-right",
+        "move the pane's left or right edge right",
+    ),
+    (
+        Command::GrowUp,
+        "grow-up",
+        "move the pane's top or bottom edge up",
+    ),
+    (
+        Command::GrowDown,
+        "grow-down",
+        "move the pane's top or bottom edge down",
+    ),
+    (
+        Command::CycleLineNumbers,
+        "cycle-line-numbers",
+        "cycle line numbers: off, absolute, relative",
+    ),
+    (
+        Command::ToggleWrap,
+        "toggle-wrap",
+        "toggle soft wrapping of long lines",
+    ),
+    (
+        Command::ToggleVi,
+        "toggle-vi",
+        "toggle vi style modal editing",
+    ),
+    (
+        Command::CycleLineEndings,
+        "cycle-line-endings",
+        "convert all line endings to the next style (LF, CRLF, CR)",
+    ),
+    (
+        Command::CommandLine,
+        "command-line",
+        "type a command, such as :w or :goto 10",
+    ),
+    (
+        Command::RecordMacro,
+        "record-macro",
+        "start recording keys into a register, or stop recording",
+    ),
+    (
+        Command::PlayMacro,
+        "play-macro",
+        "play the macro in a register, a count typed first plays it again",
+    ),
+    (
+        Command::SaveMacros,
+        "save-macros",
+        "save the recorded macros for next time",
+    ),
+    (Command::Refresh, "refresh", "refresh the screen"),
+    (Command::Exit, "exit", "exit"),
+];
+
+/// the bindings used when there is no config file
+const DEFAULT_BINDINGS: &[(&str, Command)] = &[
+    ("f1", Command::Help),
+    ("up", Command::MoveUp),
+    ("down", Command::MoveDown),
+    ("left", Command::MoveLeft),
+    ("right", Command::MoveRight),
+    ("home", Command::LineStart),
+    ("end", Command::LineEnd),
+    ("shift+left", Command::SelectLeft),
+    ("shift+right", Command::SelectRight),
+    ("shift+up", Command::SelectUp),
+    ("shift+down", Command::SelectDown),
+    ("shift+home", Command::SelectLineStart),
+    ("shift+end", Command::SelectLineEnd),
+    ("ctrl+shift+left", Command::SelectWordLeft),
+    ("ctrl+shift+right", Command::SelectWordRight),
+    ("ctrl+shift+up", Command::SelectLinesUp),
+    ("ctrl+shift+down", Command::SelectLinesDown),
+    ("alt+shift+left", Command::BlockLeft),
+    ("alt+shift+right", Command::BlockRight),
+    ("alt+shift+up", Command::BlockUp),
+    ("alt+shift+down", Command::BlockDown),
+    ("ctrl+alt+up", Command::AddCursorAbove),
+    ("ctrl+alt+down", Command::AddCursorBelow),
+    ("ctrl+d", Command::AddNextMatch),
+    ("esc", Command::SingleCursor),
+    ("ctrl+up", Command::ScrollUp),
+    ("ctrl+down", Command::ScrollDown),
+    ("ctrl+left", Command::ScrollLeft),
+    ("ctrl+right", Command::ScrollRight),
+    ("ctrl+a", Command::CursorToMiddle),
+    ("ctrl+l", Command::CenterView),
+    ("enter", Command::Newline),
+    ("backspace", Command::DeleteBackward),
+    ("ctrl+z", Command::Undo),
+    ("ctrl+y", Command::Redo),
+    ("ctrl+c", Command::Copy),
+    ("ctrl+x", Command::Cut),
+    ("alt+y", Command::PasteOlder),
+    ("alt+c", Command::CopyToRegister),
+    ("alt+p", Command::PasteFromRegister),
+    ("ctrl+v", Command::Paste),
+    ("ctrl+f", Command::Search),
+    ("ctrl+r", Command::Replace),
+    ("ctrl+s", Command::Save),
+    ("ctrl+pagedown", Command::NextBuffer),
+    ("ctrl+pageup", Command::PreviousBuffer),
+    ("f3", Command::ListBuffers),
+    ("ctrl+w", Command::CloseBuffer),
+    ("alt+s", Command::SplitHorizontal),
+    ("alt+v", Command::SplitVertical),
+    ("alt+q", Command::ClosePane),
+    ("alt+left", Command::FocusLeft),
+    ("alt+right", Command::FocusRight),
+    ("alt+up", Command::FocusUp),
+    ("alt+down", Command::FocusDown),
+    ("ctrl+alt+shift+left", Command::GrowLeft),
+    ("ctrl+alt+shift+right", Command::GrowRight),
+    ("ctrl+alt+shift+up", Command::GrowUp),
+    ("ctrl+alt+shift+down", Command::GrowDown),
+    ("f4", Command::CycleLineNumbers),
+    ("alt+z", Command::ToggleWrap),
+    ("f8", Command::ToggleVi),
+    ("f7", Command::CycleLineEndings),
+    ("alt+x", Command::CommandLine),
+    ("f9", Command::RecordMacro),
+    ("f10", Command::PlayMacro),
+    ("f5", Command::Refresh),
+    ("ctrl+b", Command::Exit),
+];
+
+impl Command {
+    /// return the name of the command used in the config file
+    pub fn name(self) -> &'static str {
+        COMMANDS.iter().find(|c| c.0 == self).map_or("", |c| c.1)
+    }
+
+    /// return the command called `name`
+    pub fn from_name(name: &str) -> Option<Command> {
+        COMMANDS.iter().find(|c| c.1 == name).map(|c| c.0)
+    }
+
+    pub fn description(self) -> &'static str {
+        COMMANDS.iter().find(|c| c.0 == self).map_or("", |c| c.2)
+    }
+
+    /// return the name of every command, in the order they are listed on the help screen
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        COMMANDS.iter().map(|c| c.1)
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// return the key the way it is looked up in a keymap. the case of a character already says
+/// whether shift was held, so shift is dropped from character keys
+pub fn normalize(key: KeyEvent) -> KeyEvent {
+    match key.code {
+        KeyCode::Char(_) => KeyEvent::new(key.code, key.modifiers - KeyModifiers::SHIFT),
+        _ => key,
+    }
+}
+
+/// return the character a key types, if it types one. ctrl or alt make a key into a
+/// shortcut, except that AltGr arrives as ctrl+alt, so characters other than plain letters
+/// are typed with both held. the tab key types a tab
+pub fn typed_char(key: KeyEvent) -> Option<char> {
+    let held = key.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT);
+    let alt_gr = held == KeyModifiers::CONTROL | KeyModifiers::ALT;
+    match key.code {
+        KeyCode::Tab if held.is_empty() => Some('\t'),
+        KeyCode::Char(c) if held.is_empty() || (alt_gr && !c.is_ascii_alphabetic()) => Some(c),
+        _ => None,
+    }
+}
+
+/// names of the keys which aren't written as a single character
+const KEY_NAMES: &[(KeyCode, &str)] = &[
+    (KeyCode::Backspace, "Backspace"),
+    (KeyCode::Enter, "Enter"),
+    (KeyCode::Left, "Left"),
+    (KeyCode::Right, "Right"),
+    (KeyCode::Up, "Up"),
+    (KeyCode::Down, "Down"),
+    (KeyCode::Home, "Home"),
+    (KeyCode::End, "End"),
+    (KeyCode::PageUp, "PageUp"),
+    (KeyCode::PageDown, "PageDown"),
+    (KeyCode::Tab, "Tab"),
+    (KeyCode::BackTab, "BackTab"),
+    (KeyCode::Delete, "Delete"),
+    (KeyCode::Insert, "Insert"),
+    (KeyCode::Esc, "Esc"),
+    (KeyCode::Char(' '), "Space"),
+];
+
+/// parse a key such as `ctrl+s`, `^s`, `alt+shift+left` or `f5`.
+/// modifiers and key names are case insensitive, `shift+a` is the same as `A`
+pub fn parse_key(text: &str) -> Option<KeyEvent> {
+    let mut modifiers = KeyModifiers::empty();
+    let mut rest = text;
+    if let Some(key) = rest.strip_prefix('^').filter(|k| !k.is_empty()) {
+        modifiers |= KeyModifiers::CONTROL;
+        rest = key;
+    }
+    loop {
+        let (modifier, key) = match rest.find('+') {
+            // a trailing + is the key itself
+            Some(i) if i + 1 < rest.len() => (&rest[..i], &rest[i + 1..]),
+            _ => break,
+        };
+        modifiers |= match modifier.to_lowercase().as_str() {
+            "ctrl" | "control" => KeyModifiers::CONTROL,
+            "alt" => KeyModifiers::ALT,
+            "shift" => KeyModifiers::SHIFT,
+            _ => return None,
+        };
+        rest = key;
+    }
+
+    let mut chars = rest.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+            KeyCode::Char(c.to_uppercase().next().unwrap_or(c))
+        }
+        // terminals report control and a letter in lower case unless shift is held
+        (Some(c), None) if modifiers.contains(KeyModifiers::CONTROL) => {
+            KeyCode::Char(c.to_lowercase().next().unwrap_or(c))
+        }
+        (Some(c), None) => KeyCode::Char(c),
+        _ => {
+            let lower = rest.to_lowercase();
+            match lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                Some(n) => KeyCode::F(n),
+                None => KEY_NAMES.iter().find(|k| k.1.to_lowercase() == lower)?.0,
+            }
+        }
+    };
+    Some(normalize(KeyEvent::new(code, modifiers)))
+}
+
+/// return the name of a key as shown on the help screen, such as `^S` or `Alt+Shift+Left`
+pub fn key_name(key: KeyEvent) -> String {
+    let mut name = String::new();
+    if key.modifiers.contains(KeyModifiers::ALT) {
+        name.push_str("Alt+");
+    }
+    if key.modifiers.contains(KeyModifiers::SHIFT) {
+        name.push_str("Shift+");
+    }
+    match (key.code, KEY_NAMES.iter().find(|k| k.0 == key.code)) {
+        (_, Some((_, text))) => name.push_str(text),
+        (KeyCode::F(n), _) => name.push_str(&format!("F{}", n)),
+        (KeyCode::Char(c), _) if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            // control letters are written in upper case, so shift has to be spelled out
+            if c.is_uppercase() {
+                name.push_str("Shift+");
+            }
+            name.extend(c.to_uppercase())
+        }
+        (KeyCode::Char(c), _) => name.push(c),
+        (code, _) => name.push_str(&format!("{:?}", code)),
+    }
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        name.insert(0, '^');
+    }
+    name
+}
+
+/// The commands bound to each key
+#[derive(Clone, Debug)]
+pub struct Keymap {
+    bindings: HashMap<KeyEvent, Command>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut keymap = Self::empty();
+        for (key, command) in DEFAULT_BINDINGS {
+            keymap.bind(parse_key(key).expect("invalid default binding"), *command);
+        }
+        keymap
+    }
+}
+
+impl Keymap {
+    /// return a keymap with nothing bound
+    pub fn empty() -> Self {
+        Self {
+            bindings: HashMap::new(),
+        }
+    }
+
+    pub fn bind(&mut self, key: KeyEvent, command: Command) {
+        self.bindings.insert(normalize(key), command);
+    }
+
+    pub fn unbind(&mut self, key: KeyEvent) {
+        self.bindings.remove(&normalize(key));
+    }
+
+    /// return the command bound to `key`
+    pub fn get(&self, key: KeyEvent) -> Option<Command> {
+        self.bindings.get(&normalize(key)).copied()
+    }
+
+    /// return the keys bound to `command`, sorted by their names
+    pub fn keys(&self, command: Command) -> Vec<KeyEvent> {
+        let mut keys: Vec<KeyEvent> = self
+            .bindings
+            .iter()
+            .filter(|(_, c)| **c == command)
+            .map(|(k, _)| *k)
+            .collect();
+        keys.sort_by_key(|k| key_name(*k));
+        keys
+    }
+
+    /// return the help screen, listing every bound command with its keys
+    pub fn help_text(&self) -> String {
+        const KEY_COLUMN: usize = 24;
+        let mut text = String::from(
+            "[ =================== Keybindings Reference ======================= ]\n\
+             [^] = Ctrl, [|] = Or\n\n",
+        );
+        for (command, _, description) in COMMANDS {
+            let keys = self.keys(*command);
+            if keys.is_empty() {
+                continue;
+            }
+            let keys: Vec<String> = keys.into_iter().map(key_name).collect();
+            let keys = keys.join("|");
+            let indent = " ".repeat(KEY_COLUMN);
+            let description = description.replace('\n', &format!("\n{}", indent));
+            if keys.len() < KEY_COLUMN {
+                text += &format!("{:width$}{}\n", keys, description, width = KEY_COLUMN);
+            } else {
+                text += &format!("{}\n{}{}\n", keys, indent, description);
+            }
+        }
+        text += "\npress any key to return to the editor\n";
+        text += "[ ================================================================== ]";
+        text
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_key() {
+        let ctrl_s = KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL);
+        assert_eq!(parse_key("ctrl+s"), Some(ctrl_s));
+        assert_eq!(parse_key("^s"), Some(ctrl_s));
+        assert_eq!(parse_key("Ctrl+S"), Some(ctrl_s));
+        assert_eq!(
+            parse_key("shift+a"),
+            Some(KeyEvent::from(KeyCode::Char('A')))
+        );
+        assert_eq!(
+            parse_key("alt+shift+Left"),
+            Some(KeyEvent::new(
+                KeyCode::Left,
+                KeyModifiers::ALT | KeyModifiers::SHIFT
+            ))
+        );
+        assert_eq!(parse_key("F12"), Some(KeyEvent::from(KeyCode::F(12))));
+        assert_eq!(
+            parse_key("ctrl++"),
+            Some(KeyEvent::new(KeyCode::Char('+'), KeyModifiers::CONTROL))
+        );
+        assert_eq!(parse_key("hyper+x"), None);
+        assert_eq!(parse_key("nope"), None);
+
+        for key in &[
+            "^S",
+            "^Shift+S",
+            "Alt+Shift+Left",
+            "F5",
+            "Space",
+            "^PageDown",
+        ] {
+            assert_eq!(key_name(parse_key(key).unwrap()), *key);
+        }
+    }
+
+    #[test]
+    fn test_typed_char() {
+        let key = |c, modifiers| typed_char(KeyEvent::new(KeyCode::Char(c), modifiers));
+        assert_eq!(key('a', KeyModifiers::empty()), Some('a'));
+        assert_eq!(key('A', KeyModifiers::SHIFT), Some('A'));
+        assert_eq!(key('s', KeyModifiers::CONTROL), None);
+        assert_eq!(key('x', KeyModifiers::ALT), None);
+        // unbound shortcuts on other keys don't type anything
+        assert_eq!(key(' ', KeyModifiers::CONTROL), None);
+        assert_eq!(key('1', KeyModifiers::ALT), None);
+        assert_eq!(key('[', KeyModifiers::CONTROL), None);
+        assert_eq!(key('.', KeyModifiers::ALT | KeyModifiers::SHIFT), None);
+        assert_eq!(key('é', KeyModifiers::ALT), None);
+        // AltGr, which arrives as ctrl+alt
+        let alt_gr = KeyModifiers::CONTROL | KeyModifiers::ALT;
+        assert_eq!(key('@', alt_gr), Some('@'));
+        assert_eq!(key('ł', alt_gr), Some('ł'));
+        assert_eq!(key('Ł', alt_gr | KeyModifiers::SHIFT), Some('Ł'));
+        assert_eq!(key('q', alt_gr), None);
+        assert_eq!(typed_char(KeyEvent::from(KeyCode::Enter)), None);
+        assert_eq!(typed_char(KeyEvent::from(KeyCode::Tab)), Some('\t'));
+        assert_eq!(typed_char(KeyEvent::new(KeyCode::Tab, KeyModifiers::CONTROL)), None);
+    }
+
+    #[test]
+    fn test_keymap() {
+        let mut keymap = Keymap::default();
+        assert_eq!(
+            keymap.get(KeyEvent::new(KeyCode::Char('b'), KeyModifiers::CONTROL)),
+            Some(Command::Exit)
+        );
+        // shift doesn't have to be reported along with a character
+        keymap.bind(parse_key("alt+Z").unwrap(), Command::Redo);
+        assert_eq!(
+            keymap.get(KeyEvent::new(
+                KeyCode::Char('Z'),
+                KeyModifiers::ALT | KeyModifiers::SHIFT
+            )),
+            Some(Command::Redo)
+        );
+
+        keymap.unbind(parse_key("ctrl+b").unwrap());
+        keymap.bind(parse_key("ctrl+q").unwrap(), Command::Exit);
+        assert_eq!(Command::from_name("exit"), Some(Command::Exit));
+
+        // the help screen follows the bindings
+        let help = keymap.help_text();
+        assert!(help.contains("^Q                      exit\n"));
+        assert!(!help.contains("^B"));
+        assert!(help.contains("^PageDown               switch to the next buffer\n"));
+        assert!(help.contains("Alt+Z|^Y                redo the last undone edit\n"));
+    }
+}
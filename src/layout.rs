@@ -0,0 +1,353 @@
+//! split panes. the editor area is divided by a tree of splits, each leaf of which is a pane
+//! showing a buffer. the rectangles are recomputed from the tree whenever the screen is drawn,
+//! so the panes follow the size of the terminal.
+//!
+//! panes are identified by their index in the tree from left to right.
+
+use crate::editor::Vector2;
+use crate::renderer::{Rect, RenderOpts};
+
+/// The direction in which an area is divided
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Split {
+    /// one pane above the other
+    Horizontal,
+    /// panes side by side
+    Vertical,
+}
+
+/// A viewport onto a buffer
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Pane {
+    /// index of the buffer shown in the pane
+    pub buffer: usize,
+    pub render_opts: RenderOpts,
+}
+
+/// A tree of panes
+#[derive(Debug)]
+pub enum Layout {
+    Pane(Pane),
+    Split {
+        direction: Split,
+        /// share of the area given to the first child
+        ratio: f32,
+        first: Box<Layout>,
+        second: Box<Layout>,
+    },
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout::Pane(Pane::default())
+    }
+}
+
+/// divide `area` in two with a one cell wide separator between the halves
+fn divide(area: Rect, direction: Split, ratio: f32) -> (Rect, Rect, Rect) {
+    let size = match direction {
+        Split::Horizontal => area.height,
+        Split::Vertical => area.width,
+    };
+    let available = (size - 1).max(0);
+    let first = if available >= 2 {
+        ((available as f32 * ratio).round() as i32).clamp(1, available - 1)
+    } else {
+        available
+    };
+    let second = available - first;
+
+    match direction {
+        Split::Horizontal => (
+            Rect { height: first, ..area },
+            Rect {
+                location: area.location.add((0, first)),
+                height: 1,
+                ..area
+            },
+            Rect {
+                location: area.location.add((0, first + 1)),
+                height: second,
+                ..area
+            },
+        ),
+        Split::Vertical => (
+            Rect { width: first, ..area },
+            Rect {
+                location: area.location.add((first, 0)),
+                width: 1,
+                ..area
+            },
+            Rect {
+                location: area.location.add((first + 1, 0)),
+                width: second,
+                ..area
+            },
+        ),
+    }
+}
+
+impl Layout {
+    pub fn new(pane: Pane) -> Self {
+        Layout::Pane(pane)
+    }
+
+    /// return the number of panes
+    pub fn pane_count(&self) -> usize {
+        match self {
+            Layout::Pane(_) => 1,
+            Layout::Split { first, second, .. } => first.pane_count() + second.pane_count(),
+        }
+    }
+
+    /// return every pane in order
+    pub fn panes(&self) -> Vec<&Pane> {
+        match self {
+            Layout::Pane(pane) => vec![pane],
+            Layout::Split { first, second, .. } => {
+                let mut panes = first.panes();
+                panes.extend(second.panes());
+                panes
+            }
+        }
+    }
+
+    pub fn panes_mut(&mut self) -> Vec<&mut Pane> {
+        match self {
+            Layout::Pane(pane) => vec![pane],
+            Layout::Split { first, second, .. } => {
+                let mut panes = first.panes_mut();
+                panes.extend(second.panes_mut());
+                panes
+            }
+        }
+    }
+
+    pub fn pane(&self, index: usize) -> Option<&Pane> {
+        self.panes().into_iter().nth(index)
+    }
+
+    pub fn pane_mut(&mut self, index: usize) -> Option<&mut Pane> {
+        self.panes_mut().into_iter().nth(index)
+    }
+
+    /// lay the panes out over `area`, returning the rectangle of each pane in order and
+    /// the separators between them
+    pub fn arrange(&self, area: Rect) -> (Vec<Rect>, Vec<(Rect, Split)>) {
+        let (mut panes, mut separators) = (Vec::new(), Vec::new());
+        self.arrange_into(area, &mut panes, &mut separators);
+        (panes, separators)
+    }
+
+    fn arrange_into(&self, area: Rect, panes: &mut Vec<Rect>, separators: &mut Vec<(Rect, Split)>) {
+        match self {
+            Layout::Pane(_) => panes.push(area),
+            Layout::Split {
+                direction,
+                ratio,
+                first,
+                second,
+            } => {
+                let (a, separator, b) = divide(area, *direction, *ratio);
+                first.arrange_into(a, panes, separators);
+                separators.push((separator, *direction));
+                second.arrange_into(b, panes, separators);
+            }
+        }
+    }
+
+    /// split the pane at `index` in two, placing `pane` after it
+    ///
+    /// # Returns
+    /// false if there is no pane at `index`
+    pub fn split(&mut self, index: usize, direction: Split, pane: Pane) -> bool {
+        match self {
+            Layout::Pane(_) if index == 0 => {
+                let old = std::mem::take(self);
+                *self = Layout::Split {
+                    direction,
+                    ratio: 0.5,
+                    first: Box::new(old),
+                    second: Box::new(Layout::Pane(pane)),
+                };
+                true
+            }
+            Layout::Pane(_) => false,
+            Layout::Split { first, second, .. } => {
+                let n = first.pane_count();
+                if index < n {
+                    first.split(index, direction, pane)
+                } else {
+                    second.split(index - n, direction, pane)
+                }
+            }
+        }
+    }
+
+    /// remove the pane at `index`, giving its space to the pane or split beside it.
+    /// the last pane can't be removed
+    pub fn close(&mut self, index: usize) -> Option<Pane> {
+        let (first, second) = match self {
+            Layout::Split { first, second, .. } => (first, second),
+            Layout::Pane(_) => return None,
+        };
+        let n = first.pane_count();
+        let in_first = index < n;
+        let (target, i) = if in_first {
+            (&mut **first, index)
+        } else {
+            (&mut **second, index - n)
+        };
+        match target {
+            Layout::Split { .. } => return target.close(i),
+            Layout::Pane(_) if i != 0 => return None,
+            Layout::Pane(_) => {}
+        }
+
+        // the pane is a child of this split, the other child takes the place of the split
+        let other = if in_first {
+            std::mem::take(&mut **second)
+        } else {
+            std::mem::take(&mut **first)
+        };
+        match std::mem::replace(self, other) {
+            Layout::Split { first, second, .. } => match if in_first { *first } else { *second } {
+                Layout::Pane(pane) => Some(pane),
+                Layout::Split { .. } => None,
+            },
+            Layout::Pane(_) => None,
+        }
+    }
+
+    /// move the separator of the closest split in `direction` containing the pane at `index`
+    /// by `amount` of the split. a positive amount moves it right or down, a negative one left
+    /// or up, whichever side of it the pane is on
+    ///
+    /// # Returns
+    /// false if the pane is not within a split in that direction
+    pub fn resize(&mut self, index: usize, direction: Split, amount: f32) -> bool {
+        match self {
+            Layout::Pane(_) => false,
+            Layout::Split {
+                direction: d,
+                ratio,
+                first,
+                second,
+            } => {
+                let n = first.pane_count();
+                let resized = if index < n {
+                    first.resize(index, direction, amount)
+                } else {
+                    second.resize(index - n, direction, amount)
+                };
+                if resized {
+                    true
+                } else if *d == direction {
+                    *ratio = (*ratio + amount).clamp(0.1, 0.9);
+                    true
+                } else {
+                    false
+                }
+            }
+        }
+    }
+}
+
+/// return the index of the pane next to `rects[from]` in `direction`, choosing the closest
+/// one when there are several
+pub fn neighbor(rects: &[Rect], from: usize, direction: Vector2) -> Option<usize> {
+    let current = rects.get(from)?;
+    let overlaps = |a: i32, a_len: i32, b: i32, b_len: i32| a < b + b_len && b < a + a_len;
+
+    rects
+        .iter()
+        .enumerate()
+        .filter(|&(i, rect)| {
+            i != from
+                && match direction {
+                    Vector2(x, _) if x > 0 => rect.x() >= current.x() + current.width,
+                    Vector2(x, _) if x < 0 => rect.x() + rect.width <= current.x(),
+                    Vector2(_, y) if y > 0 => rect.y() >= current.y() + current.height,
+                    _ => rect.y() + rect.height <= current.y(),
+                }
+                && if direction.x() != 0 {
+                    overlaps(rect.y(), rect.height, current.y(), current.height)
+                } else {
+                    overlaps(rect.x(), rect.width, current.x(), current.width)
+                }
+        })
+        .min_by_key(|(_, rect)| (rect.x() - current.x()).abs() + (rect.y() - current.y()).abs())
+        .map(|(i, _)| i)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pane(buffer: usize) -> Pane {
+        Pane {
+            buffer,
+            ..Pane::default()
+        }
+    }
+
+    fn rect(x: i32, y: i32, width: i32, height: i32) -> Rect {
+        Rect {
+            location: Vector2(x, y),
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn test_layout() {
+        let mut layout = Layout::new(pane(0));
+        assert!(layout.split(0, Split::Vertical, pane(1)));
+        assert!(layout.split(1, Split::Horizontal, pane(2)));
+        assert!(!layout.split(3, Split::Horizontal, pane(3)));
+        let buffers: Vec<usize> = layout.panes().iter().map(|p| p.buffer).collect();
+        assert_eq!(buffers, vec![0, 1, 2]);
+
+        // +---+---+
+        // | 0 | 1 |
+        // |   +---+
+        // |   | 2 |
+        // +---+---+
+        let (rects, separators) = layout.arrange(rect(0, 1, 21, 11));
+        assert_eq!(
+            rects.iter().map(|r| (r.x(), r.y(), r.width, r.height)).collect::<Vec<_>>(),
+            vec![(0, 1, 10, 11), (11, 1, 10, 5), (11, 7, 10, 5)]
+        );
+        assert_eq!(separators.len(), 2);
+        assert_eq!(separators[0].1, Split::Vertical);
+        assert_eq!(separators[0].0.x(), 10);
+
+        assert_eq!(neighbor(&rects, 0, Vector2(1, 0)), Some(1));
+        assert_eq!(neighbor(&rects, 2, Vector2(0, -1)), Some(1));
+        assert_eq!(neighbor(&rects, 2, Vector2(-1, 0)), Some(0));
+        assert_eq!(neighbor(&rects, 0, Vector2(-1, 0)), None);
+
+        // moving the separator above the bottom pane up grows it
+        assert!(layout.resize(2, Split::Horizontal, -0.2));
+        assert!(!layout.resize(0, Split::Horizontal, 0.2));
+        let (rects, _) = layout.arrange(rect(0, 1, 21, 11));
+        assert_eq!((rects[2].y(), rects[2].height), (5, 7));
+
+        // the separator moves the same way from either side of it
+        assert!(layout.resize(1, Split::Vertical, -0.2));
+        let (rects, _) = layout.arrange(rect(0, 1, 21, 11));
+        assert_eq!((rects[0].width, rects[1].x(), rects[1].width), (6, 7, 14));
+        assert!(layout.resize(0, Split::Vertical, 0.2));
+        let (rects, _) = layout.arrange(rect(0, 1, 21, 11));
+        assert_eq!((rects[0].width, rects[1].x(), rects[1].width), (10, 11, 10));
+
+        // closing a pane gives its space to its sibling
+        assert_eq!(layout.close(1).map(|p| p.buffer), Some(1));
+        let (rects, _) = layout.arrange(rect(0, 1, 21, 11));
+        assert_eq!(rects[1].height, 11);
+        assert_eq!(layout.close(0).map(|p| p.buffer), Some(0));
+        assert!(layout.close(0).is_none());
+        assert_eq!(layout.pane_count(), 1);
+        assert_eq!(layout.pane(0).map(|p| p.buffer), Some(2));
+    }
+}
@@ -1,4 +1,20 @@
-pub mod application;
-pub mod clipboard;
-pub mod editor;
-pub mod renderer;
+pub mod application;
+pub mod buffer;
+pub mod clipboard;
+pub mod command_line;
+pub mod config;
+pub mod editor;
+pub mod grid;
+pub mod history;
+pub mod keymap;
+pub mod layout;
+pub mod line_ending;
+pub mod macros;
+pub mod renderer;
+pub mod screen;
+pub mod search;
+pub mod status;
+pub mod syntax;
+pub mod unicode;
+pub mod vi;
+pub mod wrap;
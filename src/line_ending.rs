@@ -0,0 +1,167 @@
+//! line ending styles.
+//! the buffer keeps every line break exactly as it was read, so files are written back
+//! byte for byte; the style is used for newly inserted lines and for conversions.
+
+use std::fmt;
+
+/// The character sequence which ends a line
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LineEnding {
+    /// `\n`, used on unix systems
+    Lf,
+    /// `\r\n`, used on windows
+    CrLf,
+    /// `\r`, used by classic mac os
+    Cr,
+}
+
+impl Default for LineEnding {
+    fn default() -> Self {
+        if cfg!(windows) {
+            LineEnding::CrLf
+        } else {
+            LineEnding::Lf
+        }
+    }
+}
+
+impl LineEnding {
+    /// return the characters making up the line ending
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+
+    /// return the line ending at the end of `line`, if any
+    pub fn of(line: &str) -> Option<Self> {
+        if line.ends_with("\r\n") {
+            Some(LineEnding::CrLf)
+        } else if line.ends_with('\n') {
+            Some(LineEnding::Lf)
+        } else if line.ends_with('\r') {
+            Some(LineEnding::Cr)
+        } else {
+            None
+        }
+    }
+
+    /// return the next style in the order LF, CRLF, CR
+    pub fn next(&self) -> Self {
+        match self {
+            LineEnding::Lf => LineEnding::CrLf,
+            LineEnding::CrLf => LineEnding::Cr,
+            LineEnding::Cr => LineEnding::Lf,
+        }
+    }
+}
+
+impl fmt::Display for LineEnding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                LineEnding::Lf => "LF",
+                LineEnding::CrLf => "CRLF",
+                LineEnding::Cr => "CR",
+            }
+        )
+    }
+}
+
+/// split `text` into lines at line breaks of every style, the way the buffer does.
+/// like `str::lines`, a line break at the end of the text doesn't start another line
+pub fn lines(text: &str) -> Vec<&str> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c == '\n' || c == '\r' {
+            lines.push(&text[start..i]);
+            start = i + 1;
+            if c == '\r' && chars.peek().map(|&(_, c)| c) == Some('\n') {
+                chars.next();
+                start += 1;
+            }
+        }
+    }
+    if start < text.len() {
+        lines.push(&text[start..]);
+    }
+    lines
+}
+
+/// Number of line breaks of each style in a text
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct LineEndingCount {
+    pub lf: usize,
+    pub crlf: usize,
+    pub cr: usize,
+}
+
+impl LineEndingCount {
+    /// count the line breaks in `text`
+    pub fn of(text: impl IntoIterator<Item = char>) -> Self {
+        let mut count = Self::default();
+        let mut previous_cr = false;
+        for c in text {
+            match c {
+                '\n' if previous_cr => {
+                    count.cr -= 1;
+                    count.crlf += 1;
+                }
+                '\n' => count.lf += 1,
+                '\r' => count.cr += 1,
+                _ => {}
+            }
+            previous_cr = c == '\r';
+        }
+        count
+    }
+
+    /// return true if more than one style of line ending is used
+    pub fn is_mixed(&self) -> bool {
+        [self.lf, self.crlf, self.cr].iter().filter(|&&n| n > 0).count() > 1
+    }
+
+    /// return the most common style, or `None` if there are no line breaks
+    pub fn dominant(&self) -> Option<LineEnding> {
+        let counts = [
+            (self.lf, LineEnding::Lf),
+            (self.crlf, LineEnding::CrLf),
+            (self.cr, LineEnding::Cr),
+        ];
+        counts
+            .iter()
+            .filter(|(n, _)| *n > 0)
+            .fold(None, |best: Option<(usize, LineEnding)>, &(n, style)| match best {
+                Some((m, _)) if m >= n => best,
+                _ => Some((n, style)),
+            })
+            .map(|(_, style)| style)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_line_ending_count() {
+        let count = LineEndingCount::of("a\r\nb\r\nc\nd\re\r\n".chars());
+        assert_eq!(count, LineEndingCount { lf: 1, crlf: 3, cr: 1 });
+        assert!(count.is_mixed());
+        assert_eq!(count.dominant(), Some(LineEnding::CrLf));
+
+        let count = LineEndingCount::of("no breaks".chars());
+        assert!(!count.is_mixed());
+        assert_eq!(count.dominant(), None);
+
+        assert_eq!(lines("a\rb\r\nc\nd\n"), vec!["a", "b", "c", "d"]);
+        assert_eq!(lines("\r\rx"), vec!["", "", "x"]);
+        assert!(lines("").is_empty());
+    }
+}
@@ -0,0 +1,194 @@
+//! keyboard macros: the keys pressed while recording are kept in a register named by a letter,
+//! to be played back later. macros are saved next to the config file, one per line as the
+//! register followed by its keys:
+//!
+//! ```text
+//! a Home ^Right Space Down
+//! ```
+
+use crate::config::Config;
+use crate::editor;
+use crate::keymap;
+use crossterm::event::KeyEvent;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::io;
+use std::path::PathBuf;
+
+/// What the next key names the register for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MacroPrompt {
+    Record,
+    /// play the macro `count` times, the count is typed before the register
+    Play { count: Option<usize> },
+}
+
+/// The recorded macros, and the one being recorded
+#[derive(Clone, Debug, Default)]
+pub struct Macros {
+    registers: BTreeMap<char, Vec<KeyEvent>>,
+    recording: Option<(char, Vec<KeyEvent>)>,
+}
+
+impl Macros {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// return true if `name` can name a register
+    pub fn is_register(name: char) -> bool {
+        name.is_ascii_alphabetic()
+    }
+
+    /// start recording keys into the register `name`
+    pub fn start(&mut self, name: char) {
+        self.recording = Some((name, Vec::new()));
+    }
+
+    /// return the register being recorded into
+    pub fn recording(&self) -> Option<char> {
+        self.recording.as_ref().map(|(name, _)| *name)
+    }
+
+    pub fn record(&mut self, key: KeyEvent) {
+        if let Some((_, keys)) = self.recording.as_mut() {
+            keys.push(key);
+        }
+    }
+
+    /// finish recording, replacing what was in the register. returns the register
+    pub fn stop(&mut self) -> Option<char> {
+        let (name, keys) = self.recording.take()?;
+        self.registers.insert(name, keys);
+        Some(name)
+    }
+
+    pub fn get(&self, name: char) -> Option<&[KeyEvent]> {
+        self.registers.get(&name).map(Vec::as_slice)
+    }
+
+    pub fn set(&mut self, name: char, keys: Vec<KeyEvent>) {
+        self.registers.insert(name, keys);
+    }
+
+    /// return the path macros are saved to, `macros` next to the config file
+    pub fn path() -> Option<PathBuf> {
+        Config::path().map(|path| path.with_file_name("macros"))
+    }
+
+    /// read the saved macros, there are none if nothing was saved
+    pub fn load() -> Result<Macros, Box<dyn Error>> {
+        let path = match Self::path() {
+            Some(path) if path.exists() => path,
+            _ => return Ok(Macros::default()),
+        };
+        Ok(Self::parse(&editor::load(&path)?)?)
+    }
+
+    /// save every macro, returning where they were saved
+    pub fn save(&self) -> io::Result<PathBuf> {
+        let path = Self::path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        editor::store(&path, &self.to_text())?;
+        Ok(path)
+    }
+
+    /// read macros written by `to_text`
+    pub fn parse(text: &str) -> Result<Macros, String> {
+        let mut macros = Macros::new();
+        for (i, line) in text.lines().enumerate() {
+            let mut words = line.split_whitespace();
+            let name = match words.next() {
+                None => continue,
+                Some(name) if name.starts_with('#') => continue,
+                Some(name) => name,
+            };
+            let mut chars = name.chars();
+            let name = match (chars.next(), chars.next()) {
+                (Some(c), None) if Self::is_register(c) => c,
+                _ => return Err(format!("macros line {}: bad register {}", i + 1, name)),
+            };
+            let keys = words
+                .map(|word| {
+                    keymap::parse_key(word)
+                        .ok_or_else(|| format!("macros line {}: unknown key {}", i + 1, word))
+                })
+                .collect::<Result<_, _>>()?;
+            macros.set(name, keys);
+        }
+        Ok(macros)
+    }
+
+    /// write each macro on a line of its own.
+    /// keys without a name that `parse` can read back, like Null, are left out
+    pub fn to_text(&self) -> String {
+        self.registers
+            .iter()
+            .map(|(name, keys)| {
+                let keys: Vec<String> = keys.iter().filter_map(|key| saved_name(*key)).collect();
+                format!("{} {}\n", name, keys.join(" "))
+            })
+            .collect()
+    }
+}
+
+/// return the name a key is saved under, if it reads back as the same key
+fn saved_name(key: KeyEvent) -> Option<String> {
+    let name = keymap::key_name(key);
+    let readable = !name.contains(char::is_whitespace)
+        && keymap::parse_key(&name) == Some(keymap::normalize(key));
+    if readable {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    #[test]
+    fn test_macros() {
+        let keys = vec![
+            KeyEvent::from(KeyCode::Home),
+            KeyEvent::new(KeyCode::Right, KeyModifiers::CONTROL),
+            KeyEvent::from(KeyCode::Char(' ')),
+            KeyEvent::from(KeyCode::Char('+')),
+            KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL),
+            KeyEvent::from(KeyCode::Char('X')),
+        ];
+        let mut macros = Macros::new();
+        macros.start('a');
+        assert_eq!(macros.recording(), Some('a'));
+        for key in &keys {
+            macros.record(*key);
+        }
+        assert_eq!(macros.stop(), Some('a'));
+        assert_eq!(macros.recording(), None);
+        assert_eq!(macros.get('a'), Some(keys.as_slice()));
+
+        let text = macros.to_text();
+        assert_eq!(text, "a Home ^Right Space + ^S X\n");
+        let loaded = Macros::parse(&format!("# saved macros\n\n{}", text)).unwrap();
+        assert_eq!(loaded.get('a'), Some(keys.as_slice()));
+
+        // keys which can't be read back are left out instead of spoiling the file
+        let mut odd = keys.clone();
+        odd.insert(1, KeyEvent::from(KeyCode::Null));
+        macros.set('b', odd);
+        let text = macros.to_text();
+        assert_eq!(text, "a Home ^Right Space + ^S X\nb Home ^Right Space + ^S X\n");
+        assert_eq!(Macros::parse(&text).unwrap().get('b'), Some(keys.as_slice()));
+
+        assert!(Macros::parse("ab Home").is_err());
+        assert_eq!(
+            Macros::parse("a Home Nowhere").unwrap_err(),
+            "macros line 1: unknown key Nowhere"
+        );
+    }
+}
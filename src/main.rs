@@ -1,13 +1,39 @@
 use rust_ed::application::Application;
 use rust_ed::clipboard::OsClipboard;
+use rust_ed::config::Config;
 use rust_ed::editor::Editor;
+use rust_ed::macros::Macros;
 use std::error::Error;
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let mut app = Application::new(
-        Editor::from(include_str!("../resources/sample_text.txt")),
-        OsClipboard::new()?,
-    );
+    // open every file given on the command line, or start with an empty buffer
+    let mut editors = Vec::new();
+    for path in std::env::args_os().skip(1) {
+        editors.push(Editor::open(path)?);
+    }
+    let mut editors = editors.into_iter();
+
+    let mut app = Application::new(editors.next().unwrap_or_default(), OsClipboard::new()?);
+    for editor in editors {
+        app.open(editor);
+    }
+    // start on the first file
+    app.select_buffer(0);
+
+    // a broken config file shouldn't keep the editor from starting, the defaults are used instead
+    match Config::load() {
+        Ok(config) => {
+            app.keymap = config.keymap;
+            for setting in config.settings {
+                app.set(setting);
+            }
+        }
+        Err(e) => app.log = format!("error reading config: {}", e),
+    }
+    match Macros::load() {
+        Ok(macros) => app.macros = macros,
+        Err(e) => app.log = format!("error reading macros: {}", e),
+    }
 
     app.run()?;
 
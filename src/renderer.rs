@@ -1,11 +1,29 @@
 //! handles rendering an editor state
 
-use crate::editor::{Editor, Vector2};
+use crate::editor::{CharCel, Editor, Vector2, SECONDARY_CURSOR_COLOR};
+use crate::unicode;
+use crate::wrap::{self, VisualRow};
+use crossterm::cursor::MoveTo;
+use crossterm::style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor};
+use crossterm::QueueableCommand;
+use std::io::Write;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// color of the line numbers in the gutter
+const LINE_NUMBER_COLOR: Color = Color::DarkGrey;
+/// color of the number of the line the cursor is on
+const CURRENT_LINE_NUMBER_COLOR: Color = Color::Yellow;
 
 /// contains parameters for rendering
 #[derive(Clone, Copy, Debug)]
 pub struct RenderOpts {
+    /// the part of the buffer in view, measured in screen columns. the gutter is drawn to the
+    /// left of it and is not included in its width
     pub view: Rect,
+    pub gutter: Gutter,
+    /// wrap long lines over several rows instead of scrolling sideways.
+    /// see the `wrap` module for how the view is interpreted
+    pub wrap: bool,
 }
 
 impl Default for RenderOpts {
@@ -16,7 +34,210 @@ impl Default for RenderOpts {
                 width: 0,
                 height: 0,
             },
+            gutter: Gutter::default(),
+            wrap: false,
+        }
+    }
+}
+
+impl RenderOpts {
+    /// return the number of screen columns taken up by the gutter when rendering `editor`
+    pub fn gutter_width(&self, editor: &Editor) -> i32 {
+        self.gutter.columns(editor.line_count())
+    }
+
+    /// return the screen rows in view and the screen column of each row's text at the left edge
+    fn rows(&self, editor: &Editor) -> (Vec<VisualRow>, i32) {
+        if self.wrap {
+            return (wrap::visible_rows(editor, self.view), 0);
+        }
+        let rows = (self.view.y()..self.view.y() + self.view.height)
+            .map(|y| VisualRow {
+                y,
+                start: 0,
+                end: editor.get_line(y).map_or(0, |row| row.chars().count() as i32),
+            })
+            .collect();
+        (rows, self.view.x())
+    }
+
+    /// return the furthest the view can scroll down and to the right. the view can only scroll
+    /// right as far as the longest line in view, leaving room for the cursor after its end
+    fn max_location(&self, editor: &Editor) -> Vector2 {
+        let view = self.view;
+        let longest = (view.y()..view.y() + view.height)
+            .filter_map(|y| {
+                let len = editor.get_line(y)?.chars().count() as i32;
+                Some(editor.display_column(Vector2(len, y)))
+            })
+            .max()
+            .unwrap_or(0);
+        Vector2(
+            (longest + 1 - view.width).max(0),
+            (editor.line_count() as i32 - view.height).max(0),
+        )
+    }
+
+    /// keep the view within the buffer
+    pub fn clamp(&mut self, editor: &Editor) {
+        if self.wrap {
+            wrap::clamp(editor, &mut self.view);
+            return;
         }
+        // the furthest column depends on the rows in view, so the rows are clamped first
+        self.view.location.1 = self.view.y().clamp(0, self.max_location(editor).y());
+        self.view.location.0 = self.view.x().clamp(0, self.max_location(editor).x());
+    }
+
+    /// scroll the view as little as possible so that `location` is `margins` away from its
+    /// edges, then keep it within the buffer
+    pub fn follow(&mut self, editor: &Editor, location: Vector2, margins: ScrollOff) {
+        let margins = margins.fit(self.view);
+        if self.wrap {
+            wrap::follow(editor, &mut self.view, location, margins.rows);
+            return;
+        }
+        let view = &mut self.view;
+        let column = editor.display_column(location);
+        view.location.1 = scroll_to(view.y(), view.height, location.y(), margins.rows);
+        view.location.0 = scroll_to(view.x(), view.width, column, margins.columns);
+        self.clamp(editor);
+    }
+
+    /// return `location` moved into the view, `margins` away from the edges unless the view
+    /// can't scroll any further that way
+    pub fn constrain(&self, editor: &Editor, location: Vector2, margins: ScrollOff) -> Vector2 {
+        let margins = margins.fit(self.view);
+        if self.wrap {
+            return wrap::constrain(editor, self.view, location, margins.rows);
+        }
+        let (view, max) = (self.view, self.max_location(editor));
+        let column = editor.display_column(location);
+        let y = keep_within(location.y(), view.y(), view.height, margins.rows, max.y());
+        let x = keep_within(column, view.x(), view.width, margins.columns, max.x());
+        if (x, y) == (column, location.y()) {
+            location
+        } else {
+            Vector2(editor.column_at_display(y, x), y)
+        }
+    }
+
+    /// return the gutter drawn beside `row`. only the first row of a wrapped line is numbered
+    fn gutter_label(&self, editor: &Editor, row: VisualRow, columns: i32) -> (String, Color) {
+        let y = if row.start == 0 { row.y } else { -1 };
+        self.gutter.label(y, editor.cursor_pos().y(), editor.line_count(), columns)
+    }
+}
+
+/// return the start of a view of `size` which has `target` at least `margin` from both ends,
+/// moving it as little as possible from `start`
+fn scroll_to(start: i32, size: i32, target: i32, margin: i32) -> i32 {
+    if target < start + margin {
+        target - margin
+    } else if target > start + size - 1 - margin {
+        target - (size - 1 - margin)
+    } else {
+        start
+    }
+}
+
+/// clamp `target` to a view of `size` from `start`, `margin` away from the ends the view can
+/// still scroll past. the view can scroll from 0 to `max`
+fn keep_within(target: i32, start: i32, size: i32, margin: i32, max: i32) -> i32 {
+    let low = if start > 0 { start + margin } else { start };
+    let high = if start < max { start + size - 1 - margin } else { start + size - 1 };
+    target.clamp(low, high.max(low))
+}
+
+/// The rows and columns kept between the cursor and the edges of the view
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScrollOff {
+    pub rows: i32,
+    pub columns: i32,
+}
+
+impl Default for ScrollOff {
+    fn default() -> Self {
+        Self { rows: 3, columns: 5 }
+    }
+}
+
+impl ScrollOff {
+    /// shrink the margins to leave room for the cursor in `view`
+    fn fit(self, view: Rect) -> Self {
+        Self {
+            rows: self.rows.min((view.height - 1) / 2).max(0),
+            columns: self.columns.min((view.width - 1) / 2).max(0),
+        }
+    }
+}
+
+/// How line numbers are shown in the gutter
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineNumbers {
+    /// no gutter is drawn
+    Off,
+    Absolute,
+    /// the distance of each line from the cursor, the cursor's line shows its own number
+    Relative,
+}
+
+impl LineNumbers {
+    /// return the mode after this one, cycling back to `Off`
+    pub fn next(self) -> Self {
+        match self {
+            LineNumbers::Off => LineNumbers::Absolute,
+            LineNumbers::Absolute => LineNumbers::Relative,
+            LineNumbers::Relative => LineNumbers::Off,
+        }
+    }
+}
+
+/// The column of line numbers to the left of the text
+#[derive(Clone, Copy, Debug)]
+pub struct Gutter {
+    pub numbers: LineNumbers,
+    /// width of the gutter in columns, including the space before the text.
+    /// the gutter grows if the line numbers don't fit
+    pub min_width: i32,
+}
+
+impl Default for Gutter {
+    fn default() -> Self {
+        Self {
+            numbers: LineNumbers::Off,
+            min_width: 4,
+        }
+    }
+}
+
+impl Gutter {
+    /// return the width of the gutter for a buffer of `line_count` lines, zero if it is off
+    pub fn columns(&self, line_count: usize) -> i32 {
+        if self.numbers == LineNumbers::Off {
+            return 0;
+        }
+        let digits = line_count.max(1).to_string().len() as i32;
+        self.min_width.max(digits + 1)
+    }
+
+    /// return the text of the gutter on row `y`, right aligned to `columns`,
+    /// along with its color
+    pub fn label(&self, y: i32, cursor_y: i32, line_count: usize, columns: i32) -> (String, Color) {
+        let columns = columns as usize;
+        if y < 0 || y as usize >= line_count {
+            return (" ".repeat(columns), LINE_NUMBER_COLOR);
+        }
+        let number = match self.numbers {
+            LineNumbers::Relative if y != cursor_y => (y - cursor_y).abs(),
+            _ => y + 1,
+        };
+        let color = if y == cursor_y {
+            CURRENT_LINE_NUMBER_COLOR
+        } else {
+            LINE_NUMBER_COLOR
+        };
+        (format!("{:>1$} ", number, columns.saturating_sub(1)), color)
     }
 }
 
@@ -41,8 +262,8 @@ impl Rect {
     }
 
     pub fn contains(&self, p: Vector2) -> bool {
-        return (p.x() >= self.location.x() && p.x() < self.location.x() + self.width)
-            && (p.y() >= self.location.y() && p.y() < self.location.y() + self.height);
+        (p.x() >= self.location.x() && p.x() < self.location.x() + self.width)
+            && (p.y() >= self.location.y() && p.y() < self.location.y() + self.height)
     }
 }
 
@@ -52,61 +273,63 @@ pub trait Renderer {
 }
 
 /// renders an editor state to a string
-pub struct StringRenderer {
-    // only render a particular line in the editor
-    pub line_hint: Option<i32>,
-    pub break_on_line_end: bool,
-}
+#[derive(Default)]
+pub struct StringRenderer;
 
 impl StringRenderer {
     pub fn new() -> Self {
-        Self {
-            line_hint: None,
-            break_on_line_end: false,
-        }
-    }
-
-    pub fn with_line_hint(line: i32) -> Self {
-        Self {
-            line_hint: Some(line),
-            break_on_line_end: false,
-        }
+        Self
     }
 }
 
 impl Renderer for StringRenderer {
     type Output = String;
 
+    /// the view is measured in screen columns.
+    /// wide characters take up two columns, and a wide character cut off by either edge of
+    /// the view is drawn as spaces
     fn render(&self, editor: &Editor, opts: RenderOpts) -> Self::Output {
         // draw the rectangle
         let mut screen: String = String::with_capacity(opts.view.area() as usize);
 
         let width = opts.view.width;
 
-        let height = if let Some(_) = self.line_hint {
-            1
-        } else {
-            opts.view.height
-        };
+        let (rows, x2) = opts.rows(editor);
+        let gutter = opts.gutter_width(editor);
 
-        let y2 = if let Some(line) = self.line_hint {
-            line
-        } else {
-            opts.view.location.y()
-        };
+        for visual in rows {
+            if gutter > 0 {
+                screen.push_str(&opts.gutter_label(editor, visual, gutter).0);
+            }
+
+            // number of screen columns filled on this line
+            let mut filled = 0;
+            let row = line_part(editor, visual);
 
-        let x2 = opts.view.location.x();
+            // tabs reach to tab stops counted from the start of the line
+            let base = editor.display_column(Vector2(visual.start, visual.y));
+            let mut column = 0;
+            for g in row.graphemes(true) {
+                let w = unicode::width_at(g, (base + column) as usize, editor.tab_width()) as i32;
+                let (start, end) = (column - x2, column - x2 + w);
+                column += w;
 
-        for y in y2..y2 + height {
-            for x in x2..x2 + width {
-                if let Some(cell) = editor.get_cell((x, y)) {
-                    screen.push(cell.char);
-                } else if self.break_on_line_end && x > 0 {
+                if end <= 0 {
+                    continue;
+                } else if start >= width {
                     break;
+                } else if start < 0 || end > width {
+                    // partially visible
+                    let visible = end.min(width) - start.max(0);
+                    screen.extend(std::iter::repeat_n(' ', visible as usize));
+                    filled += visible;
                 } else {
-                    screen.push(' ');
+                    screen.push_str(&printable(g, w));
+                    filled += w;
                 }
             }
+
+            screen.extend(std::iter::repeat_n(' ', (width - filled).max(0) as usize));
             screen.push('\n')
         }
 
@@ -114,6 +337,185 @@ impl Renderer for StringRenderer {
     }
 }
 
+/// A terminal command produced by `StyledRenderer`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Draw {
+    /// move to a column and row of the screen
+    MoveTo(u16, u16),
+    Foreground(Color),
+    Background(Color),
+    Print(String),
+    ResetColor,
+}
+
+impl Draw {
+    /// queue the command on `w` without flushing
+    pub fn queue(&self, w: &mut impl Write) -> crossterm::Result<()> {
+        match self {
+            Draw::MoveTo(x, y) => w.queue(MoveTo(*x, *y))?,
+            Draw::Foreground(color) => w.queue(SetForegroundColor(*color))?,
+            Draw::Background(color) => w.queue(SetBackgroundColor(*color))?,
+            Draw::Print(text) => w.queue(Print(text))?,
+            Draw::ResetColor => w.queue(ResetColor)?,
+        };
+        Ok(())
+    }
+}
+
+/// queue every command on `w` and flush it
+pub fn draw(commands: &[Draw], w: &mut impl Write) -> crossterm::Result<()> {
+    for command in commands {
+        command.queue(w)?;
+    }
+    w.flush()?;
+    Ok(())
+}
+
+/// renders an editor state to terminal commands, keeping the colors of each cell.
+/// rows are drawn from the top left of the screen and runs of cells with the same colors
+/// are printed together
+#[derive(Default)]
+pub struct StyledRenderer;
+
+impl StyledRenderer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// collects printed text into commands, only switching colors when they change
+#[derive(Default)]
+pub struct Runs {
+    commands: Vec<Draw>,
+    text: String,
+    fg: Option<Color>,
+    bg: Option<Color>,
+}
+
+impl Runs {
+    fn flush(&mut self) {
+        if !self.text.is_empty() {
+            self.commands.push(Draw::Print(std::mem::take(&mut self.text)));
+        }
+    }
+
+    pub fn move_to(&mut self, x: u16, y: u16) {
+        self.flush();
+        self.commands.push(Draw::MoveTo(x, y));
+    }
+
+    pub fn print(&mut self, text: &str, fg: Color, bg: Color) {
+        if self.fg != Some(fg) {
+            self.flush();
+            self.commands.push(Draw::Foreground(fg));
+            self.fg = Some(fg);
+        }
+        if self.bg != Some(bg) {
+            self.flush();
+            self.commands.push(Draw::Background(bg));
+            self.bg = Some(bg);
+        }
+        self.text.push_str(text);
+    }
+
+    /// return the commands, resetting the colors at the end
+    pub fn finish(mut self) -> Vec<Draw> {
+        self.flush();
+        self.commands.push(Draw::ResetColor);
+        self.commands
+    }
+}
+
+/// return the text to draw for a grapheme cluster taking up `width` columns,
+/// a tab is drawn as spaces up to the tab stop
+fn printable(g: &str, width: i32) -> std::borrow::Cow<'_, str> {
+    if g == "\t" {
+        " ".repeat(width as usize).into()
+    } else {
+        unicode::printable(g)
+    }
+}
+
+/// return the part of a line drawn on a screen row
+fn line_part(editor: &Editor, row: VisualRow) -> String {
+    let line = editor.get_line(row.y).unwrap_or_default();
+    if row.start == 0 && row.end as usize >= line.chars().count() {
+        return line;
+    }
+    line.chars().skip(row.start as usize).take((row.end - row.start) as usize).collect()
+}
+
+/// return the colors of a cell, using the terminal defaults where it has none
+fn colors(cell: Option<CharCel>) -> (Color, Color) {
+    match cell {
+        Some(cell) => (
+            if cell.fg_on { cell.fg } else { Color::Reset },
+            if cell.bg_on { cell.bg } else { Color::Reset },
+        ),
+        None => (Color::Reset, Color::Reset),
+    }
+}
+
+impl Renderer for StyledRenderer {
+    type Output = Vec<Draw>;
+
+    /// wide characters are laid out the same way as by `StringRenderer`
+    fn render(&self, editor: &Editor, opts: RenderOpts) -> Self::Output {
+        let mut runs = Runs::default();
+        let width = opts.view.width;
+        let (rows, x2) = opts.rows(editor);
+        let gutter = opts.gutter_width(editor);
+
+        for (i, visual) in rows.into_iter().enumerate() {
+            let y = visual.y;
+            runs.move_to(0, i as u16);
+            if gutter > 0 {
+                let (label, fg) = opts.gutter_label(editor, visual, gutter);
+                runs.print(&label, fg, Color::Reset);
+            }
+            let mut filled = 0;
+            let row = line_part(editor, visual);
+
+            let base = editor.display_column(Vector2(visual.start, y));
+            let (mut column, mut char_column) = (0, visual.start);
+            for g in row.graphemes(true) {
+                let w = unicode::width_at(g, (base + column) as usize, editor.tab_width()) as i32;
+                let (start, end) = (column - x2, column - x2 + w);
+                let (fg, bg) = colors(editor.get_cell(Vector2(char_column, y)));
+                column += w;
+                char_column += g.chars().count() as i32;
+
+                if end <= 0 {
+                    continue;
+                } else if start >= width {
+                    break;
+                } else if start < 0 || end > width {
+                    // partially visible
+                    let visible = end.min(width) - start.max(0);
+                    runs.print(&" ".repeat(visible as usize), fg, bg);
+                    filled += visible;
+                } else {
+                    runs.print(&printable(g, w), fg, bg);
+                    filled += w;
+                }
+            }
+            // a secondary cursor after the end of the line has no cell to color
+            let end = column - x2;
+            let cursor = Vector2(char_column, y);
+            let past_end = end >= 0 && end < width && editor.get_cell(cursor).is_none();
+            if past_end && filled == end && editor.has_secondary_cursor(cursor) {
+                runs.print(" ", Color::Reset, SECONDARY_CURSOR_COLOR);
+                filled += 1;
+            }
+
+            let padding = " ".repeat((width - filled).max(0) as usize);
+            runs.print(&padding, Color::Reset, Color::Reset);
+        }
+
+        runs.finish()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     //    use super::*;
@@ -135,4 +537,213 @@ mod tests {
     //        let text = renderer.render(&editor, renderOpts);
     //        panic!("\n{}", text);
     //    }
+
+    use super::*;
+
+    #[test]
+    fn test_string_renderer_wide_chars() {
+        let editor = Editor::from("ab\nこんにちは\ne\u{301}x");
+        let opts = RenderOpts {
+            view: Rect {
+                location: Vector2(1, 0),
+                width: 4,
+                height: 3,
+            },
+            ..RenderOpts::default()
+        };
+
+        let text = StringRenderer::new().render(&editor, opts);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "b   ");
+        // wide characters cut by either edge are blanked out
+        assert_eq!(lines[1], " ん ");
+        assert_eq!(lines[2], "x   ");
+    }
+
+    #[test]
+    fn test_string_renderer_tabs() {
+        let mut editor = Editor::from("a\tb\n\tcd\tef");
+        let mut opts = RenderOpts {
+            view: Rect {
+                location: Vector2(0, 0),
+                width: 8,
+                height: 2,
+            },
+            ..RenderOpts::default()
+        };
+
+        // tabs reach to the next tab stop
+        let text = StringRenderer::new().render(&editor, opts);
+        assert_eq!(text.lines().collect::<Vec<_>>(), vec!["a   b   ", "    cd  "]);
+
+        // the tab stops stay put when the view is scrolled or the line is wrapped
+        editor.set_tab_width(2);
+        opts.view.location = Vector2(1, 0);
+        let text = StringRenderer::new().render(&editor, opts);
+        assert_eq!(text.lines().collect::<Vec<_>>(), vec![" b      ", " cd  ef "]);
+
+        opts.view.location = Vector2(0, 1);
+        opts.view.width = 4;
+        opts.wrap = true;
+        let text = StringRenderer::new().render(&editor, opts);
+        assert_eq!(text.lines().collect::<Vec<_>>(), vec!["  cd", "  ef"]);
+    }
+
+    #[test]
+    fn test_styled_renderer() {
+        use crate::syntax::{Language, Token};
+
+        let mut editor = Editor::from("let x;\n1");
+        editor.set_language(Some(Language::Rust));
+        let opts = RenderOpts {
+            view: Rect {
+                location: Vector2(0, 0),
+                width: 8,
+                height: 2,
+            },
+            ..RenderOpts::default()
+        };
+
+        // the colors only change between runs
+        let commands = StyledRenderer::new().render(&editor, opts);
+        assert_eq!(
+            commands,
+            vec![
+                Draw::MoveTo(0, 0),
+                Draw::Foreground(Token::Keyword.color()),
+                Draw::Background(Color::Reset),
+                Draw::Print("let".to_string()),
+                Draw::Foreground(Color::Reset),
+                Draw::Print(" x;  ".to_string()),
+                Draw::MoveTo(0, 1),
+                Draw::Foreground(Token::Number.color()),
+                Draw::Print("1".to_string()),
+                Draw::Foreground(Color::Reset),
+                Draw::Print("       ".to_string()),
+                Draw::ResetColor,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_secondary_cursors() {
+        let mut editor = Editor::from("ab\n\ncd");
+        // the cursor added last is the primary one
+        editor.set_cursor((2, 0));
+        editor.add_cursor((0, 1));
+        editor.add_cursor((0, 2));
+        let opts = RenderOpts {
+            view: Rect {
+                location: Vector2(0, 0),
+                width: 4,
+                height: 2,
+            },
+            ..RenderOpts::default()
+        };
+
+        // cursors past the end of a line are drawn over a blank cell
+        let commands = StyledRenderer::new().render(&editor, opts);
+        assert_eq!(
+            commands,
+            vec![
+                Draw::MoveTo(0, 0),
+                Draw::Foreground(Color::Reset),
+                Draw::Background(Color::Reset),
+                Draw::Print("ab".to_string()),
+                Draw::Background(SECONDARY_CURSOR_COLOR),
+                Draw::Print(" ".to_string()),
+                Draw::Background(Color::Reset),
+                Draw::Print(" ".to_string()),
+                Draw::MoveTo(0, 1),
+                Draw::Background(SECONDARY_CURSOR_COLOR),
+                Draw::Print(" ".to_string()),
+                Draw::Background(Color::Reset),
+                Draw::Print("   ".to_string()),
+                Draw::ResetColor,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_gutter() {
+        let mut editor = Editor::from("a\nb\nc");
+        editor.set_cursor((0, 1));
+        let mut opts = RenderOpts {
+            view: Rect {
+                location: Vector2(0, 0),
+                width: 2,
+                height: 4,
+            },
+            gutter: Gutter {
+                numbers: LineNumbers::Absolute,
+                min_width: 3,
+            },
+            wrap: false,
+        };
+
+        let text = StringRenderer::new().render(&editor, opts);
+        assert_eq!(text, " 1 a \n 2 b \n 3 c \n     \n");
+
+        // relative numbers count from the cursor's line, which keeps its own number
+        opts.gutter.numbers = LineNumbers::Relative;
+        let text = StringRenderer::new().render(&editor, opts);
+        assert_eq!(text.lines().collect::<Vec<_>>(), vec![" 1 a ", " 2 b ", " 1 c ", "     "]);
+
+        // the gutter grows to fit the numbers
+        opts.gutter.min_width = 1;
+        assert_eq!(opts.gutter_width(&editor), 2);
+        opts.gutter.numbers = LineNumbers::Off;
+        assert_eq!(opts.gutter_width(&editor), 0);
+    }
+
+    #[test]
+    fn test_string_renderer_wrap() {
+        let editor = Editor::from("abcdef\nこんにちは");
+        let opts = RenderOpts {
+            view: Rect {
+                location: Vector2(1, 0),
+                width: 5,
+                height: 4,
+            },
+            gutter: Gutter {
+                numbers: LineNumbers::Absolute,
+                min_width: 2,
+            },
+            wrap: true,
+        };
+
+        // the view starts on the second row of the first line, and only the first row of a
+        // line is numbered
+        let text = StringRenderer::new().render(&editor, opts);
+        assert_eq!(text.lines().collect::<Vec<_>>(), vec!["  f    ", "2 こん ", "  にち ", "  は   "]);
+    }
+
+    #[test]
+    fn test_follow_cursor() {
+        let editor = Editor::from(&*"0123456789\n".repeat(20));
+        let mut opts = RenderOpts::default();
+        opts.view.width = 4;
+        opts.view.height = 5;
+        let margins = ScrollOff { rows: 1, columns: 1 };
+
+        // the view scrolls just enough to keep the margin below the cursor
+        opts.follow(&editor, Vector2(2, 5), margins);
+        assert_eq!(opts.view.location, Vector2(0, 2));
+        opts.follow(&editor, Vector2(6, 5), margins);
+        assert_eq!(opts.view.location, Vector2(4, 2));
+        // there is no margin at the ends of the buffer
+        opts.follow(&editor, Vector2(0, 0), margins);
+        assert_eq!(opts.view.location, Vector2(0, 0));
+        opts.follow(&editor, Vector2(10, 20), margins);
+        assert_eq!(opts.view.location, Vector2(7, 16));
+
+        opts.view.location = Vector2(-3, 30);
+        opts.clamp(&editor);
+        assert_eq!(opts.view.location, Vector2(0, 16));
+
+        // scrolling the view away drags the cursor along
+        opts.view.location = Vector2(2, 4);
+        assert_eq!(opts.constrain(&editor, Vector2(0, 1), margins), Vector2(3, 5));
+        assert_eq!(opts.constrain(&editor, Vector2(4, 6), margins), Vector2(4, 6));
+    }
 }
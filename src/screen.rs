@@ -0,0 +1,222 @@
+//! double-buffered terminal screen.
+//! each frame is drawn into a back buffer of cells, which is compared with the frame
+//! currently on the terminal so only the cells that changed are written out.
+
+use crate::renderer::{Draw, Runs};
+use crate::unicode;
+use crossterm::style::Color;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A single column of the screen
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cell {
+    /// the grapheme drawn in the cell, empty for the second column of a wide character
+    pub text: String,
+    pub fg: Color,
+    pub bg: Color,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            text: " ".to_string(),
+            fg: Color::Reset,
+            bg: Color::Reset,
+        }
+    }
+}
+
+/// The contents of the terminal, along with the frame being drawn
+pub struct Screen {
+    width: u16,
+    height: u16,
+    // the cells currently on the terminal
+    front: Vec<Cell>,
+    // the next frame
+    back: Vec<Cell>,
+    // true if the terminal may not match the front buffer and has to be redrawn entirely
+    invalid: bool,
+}
+
+impl Screen {
+    pub fn new(width: u16, height: u16) -> Self {
+        let size = width as usize * height as usize;
+        Self {
+            width,
+            height,
+            front: vec![Cell::default(); size],
+            back: vec![Cell::default(); size],
+            invalid: true,
+        }
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// change the size of the screen. the next frame is drawn in full
+    pub fn resize(&mut self, width: u16, height: u16) {
+        if (width, height) != (self.width, self.height) {
+            *self = Self::new(width, height);
+        }
+    }
+
+    /// redraw every cell on the next frame, for when something else has drawn on the terminal
+    pub fn invalidate(&mut self) {
+        self.invalid = true;
+    }
+
+    /// blank the next frame
+    pub fn clear(&mut self) {
+        for cell in self.back.iter_mut() {
+            *cell = Cell::default();
+        }
+    }
+
+    /// return the cell of the next frame at (x, y)
+    pub fn cell(&self, x: u16, y: u16) -> Option<&Cell> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.back.get(y as usize * self.width as usize + x as usize)
+    }
+
+    /// write `cell` into the next frame, blanking any wide character it overlaps
+    fn set(&mut self, x: u16, y: u16, cell: Cell) {
+        let row = y as usize * self.width as usize;
+        let i = row + x as usize;
+        if self.back[i].text.is_empty() && x > 0 {
+            self.back[i - 1] = Cell::default();
+        }
+        if x + 1 < self.width && self.back[i + 1].text.is_empty() {
+            self.back[i + 1] = Cell::default();
+        }
+        self.back[i] = cell;
+    }
+
+    /// print `text` into the next frame starting at (x, y), clipped to the end of the row.
+    /// returns the column after the text
+    pub fn print(&mut self, mut x: u16, y: u16, text: &str, fg: Color, bg: Color) -> u16 {
+        if y >= self.height {
+            return x;
+        }
+        for g in text.graphemes(true) {
+            let width = unicode::width(g) as u16;
+            if x + width > self.width {
+                // a wide character which doesn't fit is replaced by spaces
+                while x < self.width {
+                    self.set(x, y, Cell { text: " ".to_string(), fg, bg });
+                    x += 1;
+                }
+                break;
+            }
+
+            self.set(x, y, Cell { text: g.to_string(), fg, bg });
+            for k in 1..width {
+                self.set(x + k, y, Cell { text: String::new(), fg, bg });
+            }
+            x += width;
+        }
+        x
+    }
+
+    /// draw the output of a renderer into the next frame, with its top left corner at `origin`
+    pub fn apply(&mut self, commands: &[Draw], origin: (u16, u16)) {
+        let (mut x, mut y) = origin;
+        let (mut fg, mut bg) = (Color::Reset, Color::Reset);
+        for command in commands {
+            match command {
+                Draw::MoveTo(a, b) => {
+                    x = origin.0 + a;
+                    y = origin.1 + b;
+                }
+                Draw::Foreground(color) => fg = *color,
+                Draw::Background(color) => bg = *color,
+                Draw::ResetColor => {
+                    fg = Color::Reset;
+                    bg = Color::Reset;
+                }
+                Draw::Print(text) => x = self.print(x, y, text, fg, bg),
+            }
+        }
+    }
+
+    /// return the commands which turn the current frame into the next one,
+    /// and make the next frame current
+    pub fn present(&mut self) -> Vec<Draw> {
+        let mut runs = Runs::default();
+        // where the terminal cursor is after the last print
+        let mut cursor = None;
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let i = y as usize * self.width as usize + x as usize;
+                let cell = &self.back[i];
+                // the second half of a wide character is drawn along with the first
+                if cell.text.is_empty() || (!self.invalid && *cell == self.front[i]) {
+                    continue;
+                }
+
+                if cursor != Some((x, y)) {
+                    runs.move_to(x, y);
+                }
+                runs.print(&cell.text, cell.fg, cell.bg);
+                cursor = Some((x + unicode::width(&cell.text) as u16, y));
+            }
+        }
+
+        self.front.clone_from(&self.back);
+        self.invalid = false;
+        runs.finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_screen_diff() {
+        let mut screen = Screen::new(6, 2);
+        screen.print(0, 0, "hello", Color::Reset, Color::Reset);
+        let first = screen.present();
+        assert!(first.contains(&Draw::Print("hello ".to_string())));
+
+        // an identical frame draws nothing
+        screen.clear();
+        screen.print(0, 0, "hello", Color::Reset, Color::Reset);
+        assert_eq!(screen.present(), vec![Draw::ResetColor]);
+
+        // only the changed cells are written, continuing the run where cells are adjacent
+        screen.clear();
+        screen.print(0, 0, "help!", Color::Reset, Color::Reset);
+        screen.print(0, 1, "x", Color::Red, Color::Reset);
+        assert_eq!(
+            screen.present(),
+            vec![
+                Draw::MoveTo(3, 0),
+                Draw::Foreground(Color::Reset),
+                Draw::Background(Color::Reset),
+                Draw::Print("p!".to_string()),
+                Draw::MoveTo(0, 1),
+                Draw::Foreground(Color::Red),
+                Draw::Print("x".to_string()),
+                Draw::ResetColor,
+            ]
+        );
+
+        // overwriting half of a wide character blanks the other half
+        screen.clear();
+        screen.print(0, 0, "こん", Color::Reset, Color::Reset);
+        screen.print(1, 0, "a", Color::Reset, Color::Reset);
+        assert_eq!(screen.cell(0, 0).unwrap().text, " ");
+        assert_eq!(screen.cell(2, 0).unwrap().text, "ん");
+        // a wide character cut off at the end of the row is blanked
+        assert_eq!(screen.print(5, 1, "ん", Color::Reset, Color::Reset), 6);
+        assert_eq!(screen.cell(5, 1).unwrap().text, " ");
+    }
+}
@@ -0,0 +1,568 @@
+//! text search over the editor buffer.
+//! plain text matches never span more than one row, regular expression matches may.
+
+use crate::editor::{Editor, Vector2};
+use regex::{Captures, Regex, RegexBuilder};
+use std::ops::Range;
+
+/// The location of a match, `end` is exclusive
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Match {
+    pub start: Vector2,
+    pub end: Vector2,
+}
+
+/// Which way to look for the next match
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+/// A query to search for.
+/// searching is case insensitive unless the query contains an uppercase letter
+#[derive(Clone, Debug, Default)]
+pub struct Query {
+    chars: Vec<char>,
+    ignore_case: bool,
+}
+
+impl From<&str> for Query {
+    fn from(s: &str) -> Self {
+        Self {
+            chars: s.chars().collect(),
+            ignore_case: !s.chars().any(char::is_uppercase),
+        }
+    }
+}
+
+impl Query {
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+
+    fn eq(&self, a: char, b: char) -> bool {
+        if self.ignore_case {
+            a == b || a.to_lowercase().eq(b.to_lowercase())
+        } else {
+            a == b
+        }
+    }
+
+    /// return the start column of every non overlapping match in `row`
+    pub fn find_in_row(&self, row: &[char]) -> Vec<usize> {
+        let n = self.chars.len();
+        let mut found = Vec::new();
+        if n == 0 || n > row.len() {
+            return found;
+        }
+
+        let mut x = 0;
+        while x + n <= row.len() {
+            if row[x..x + n]
+                .iter()
+                .zip(self.chars.iter())
+                .all(|(&a, &b)| self.eq(a, b))
+            {
+                found.push(x);
+                x += n;
+            } else {
+                x += 1;
+            }
+        }
+        found
+    }
+
+    fn to_match(&self, x: usize, y: i32) -> Match {
+        Match {
+            start: Vector2(x as i32, y),
+            end: Vector2((x + self.chars.len()) as i32, y),
+        }
+    }
+
+    /// return every match in the rows within `rows`
+    pub fn find_all(&self, editor: &Editor, rows: Range<i32>) -> Vec<Match> {
+        let rows = rows.start.max(0)..rows.end.min(editor.line_count() as i32);
+        let mut matches = Vec::new();
+        for y in rows {
+            let row: Vec<char> = editor.get_line(y).unwrap_or_default().chars().collect();
+            matches.extend(self.find_in_row(&row).into_iter().map(|x| self.to_match(x, y)));
+        }
+        matches
+    }
+
+    /// find the closest match starting after `from` (or before it when searching backwards),
+    /// wrapping around at the end of the buffer
+    pub fn find(&self, editor: &Editor, from: Vector2, direction: Direction) -> Option<Match> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let len = editor.line_count() as i32;
+        let Vector2(x, y) = from;
+
+        // visit every row once starting from the row of `from`, and that row a second time
+        // to check the part of it on the other side of `from`
+        for i in 0..=len {
+            let row_y = match direction {
+                Direction::Forward => (y + i).rem_euclid(len),
+                Direction::Backward => (y - i).rem_euclid(len),
+            };
+            let row: Vec<char> = editor.get_line(row_y).unwrap_or_default().chars().collect();
+            let found = self.find_in_row(&row);
+
+            let hit = match (direction, i) {
+                (Direction::Forward, 0) => found.into_iter().find(|&c| c as i32 >= x),
+                (Direction::Forward, _) => found.into_iter().next(),
+                (Direction::Backward, 0) => found.into_iter().rev().find(|&c| (c as i32) < x),
+                (Direction::Backward, _) => found.into_iter().next_back(),
+            };
+
+            if let Some(c) = hit {
+                return Some(self.to_match(c, row_y));
+            }
+        }
+
+        None
+    }
+}
+
+/// State of an incremental search in progress
+pub struct IncrementalSearch {
+    pub query: String,
+    /// the match the cursor is on
+    pub current: Option<Match>,
+    /// cursor location and view offset when the search began, restored when it is cancelled
+    pub origin: Vector2,
+    pub origin_view: Vector2,
+}
+
+impl IncrementalSearch {
+    pub fn new(origin: Vector2, origin_view: Vector2) -> Self {
+        Self {
+            query: String::new(),
+            current: None,
+            origin,
+            origin_view,
+        }
+    }
+
+    /// search for the current query. the search starts at the beginning of the current
+    /// match when refining the query, so typing more characters keeps the match in place
+    pub fn update(&mut self, editor: &Editor) -> Option<Match> {
+        let from = self.current.map_or(self.origin, |m| m.start);
+        self.current = Query::from(self.query.as_str()).find(editor, from, Direction::Forward);
+        self.current
+    }
+
+    /// move to the next or previous match
+    pub fn step(&mut self, editor: &Editor, direction: Direction) -> Option<Match> {
+        let from = match (self.current, direction) {
+            (Some(m), Direction::Forward) => m.start.add((1, 0)),
+            (Some(m), Direction::Backward) => m.start,
+            (None, _) => self.origin,
+        };
+        let found = Query::from(self.query.as_str()).find(editor, from, direction);
+        if found.is_some() {
+            self.current = found;
+        }
+        found
+    }
+}
+
+/// A regular expression match and the text it will be replaced with
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replacement {
+    pub start: Vector2,
+    pub end: Vector2,
+    pub text: String,
+}
+
+/// A regular expression and a replacement template.
+/// the template may refer to capture groups as `$1` or `${name}`, and `\n` and `\t` are
+/// expanded to a line break and a tab
+pub struct RegexQuery {
+    regex: Regex,
+    template: String,
+}
+
+impl RegexQuery {
+    /// compile `pattern`. `^` and `$` match at the start and end of every line
+    pub fn new(pattern: &str, template: &str) -> Result<Self, regex::Error> {
+        let regex = RegexBuilder::new(pattern)
+            .multi_line(true)
+            .crlf(true)
+            .build()?;
+        Ok(Self {
+            regex,
+            template: unescape(template),
+        })
+    }
+
+    /// find every match between `from` and `to`, or in the whole buffer when no range is given.
+    /// the text around the range is searched too, so anchors like `$` and `\b` only match at
+    /// its ends where the text really ends, and a match running past its end is dropped
+    pub fn find_all(&self, editor: &Editor, range: Option<(Vector2, Vector2)>) -> Vec<Replacement> {
+        let text = editor.to_string();
+        let (byte_from, byte_to) = match range {
+            Some((from, to)) => (editor.location_to_byte(from), editor.location_to_byte(to)),
+            None => (0, text.len()),
+        };
+
+        let mut found = Vec::new();
+        let mut at = byte_from;
+        while let Some(captures) = self.regex.captures_at(&text, at) {
+            let m = captures.get(0).unwrap();
+            if m.end() > byte_to {
+                break;
+            }
+            found.push(self.replacement(editor, &captures));
+
+            // step over empty matches so the same location isn't matched again
+            at = if m.start() == m.end() {
+                match text[m.end()..].chars().next() {
+                    Some(c) => m.end() + c.len_utf8(),
+                    None => break,
+                }
+            } else {
+                m.end()
+            };
+        }
+        found
+    }
+
+    /// find the first match at or after `from`, as long as it ends before `limit`.
+    /// `text` is the text of `editor`, which can be kept between calls while it is unchanged
+    pub fn find_next(
+        &self,
+        editor: &Editor,
+        text: &str,
+        from: Vector2,
+        limit: Option<Vector2>,
+    ) -> Option<Replacement> {
+        let byte_to = limit.map_or(text.len(), |to| editor.location_to_byte(to));
+        let captures = self.regex.captures_at(text, editor.location_to_byte(from))?;
+        if captures.get(0).unwrap().end() > byte_to {
+            return None;
+        }
+        Some(self.replacement(editor, &captures))
+    }
+
+    /// return the location of a match and its text after expanding the template
+    fn replacement(&self, editor: &Editor, captures: &Captures) -> Replacement {
+        let m = captures.get(0).unwrap();
+        let mut text = String::new();
+        captures.expand(&self.template, &mut text);
+        Replacement {
+            start: editor.byte_to_location(m.start()),
+            end: editor.byte_to_location(m.end()),
+            text,
+        }
+    }
+}
+
+/// expand `\n`, `\t` and `\\` escapes in a replacement template
+pub fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('\\') => out.push('\\'),
+            Some(other) => {
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Apply replacements found by `RegexQuery::find_all` as a single undoable step.
+///
+/// # Returns
+/// the number of replacements made
+pub fn replace_all(editor: &mut Editor, replacements: &[Replacement]) -> usize {
+    editor.begin_group();
+    // go backwards so earlier locations are not moved by later replacements
+    for r in replacements.iter().rev() {
+        editor.replace_range(r.start, r.end, &r.text);
+    }
+    editor.end_group();
+    replacements.len()
+}
+
+/// The stage of an interactive replace
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ReplaceStep {
+    /// typing the pattern
+    Pattern,
+    /// typing the replacement template
+    Template,
+    /// asking whether to replace each match
+    Confirm,
+}
+
+/// State of a find and replace in progress
+pub struct ReplaceSession {
+    pub step: ReplaceStep,
+    pub pattern: String,
+    pub template: String,
+    pub query: Option<RegexQuery>,
+    /// the range replacements are limited to. the end is stored as a char count from the end
+    /// of the buffer so it stays in place as text before it is replaced
+    pub range: Option<(Vector2, usize)>,
+    /// the match awaiting confirmation
+    pub current: Option<Replacement>,
+    pub count: usize,
+    // the text of the buffer, kept up to date as matches are replaced so finding the next one
+    // doesn't copy the whole buffer again
+    text: Option<String>,
+}
+
+impl ReplaceSession {
+    /// start a replace over the whole buffer, or only within the selection if there is one
+    pub fn new(editor: &Editor) -> Self {
+        let range = editor
+            .selection_range()
+            .map(|(from, to)| (from, editor.len_chars() - editor.location_to_index(to)));
+        Self {
+            step: ReplaceStep::Pattern,
+            pattern: String::new(),
+            template: String::new(),
+            query: None,
+            range,
+            current: None,
+            count: 0,
+            text: None,
+        }
+    }
+
+    /// compile the pattern and template
+    pub fn compile(&mut self) -> Result<(), regex::Error> {
+        self.query = Some(RegexQuery::new(&self.pattern, &self.template)?);
+        Ok(())
+    }
+
+    fn bounds(&self, editor: &Editor) -> (Vector2, Option<Vector2>) {
+        match self.range {
+            Some((from, from_end)) => (
+                from,
+                Some(editor.index_to_location(editor.len_chars().saturating_sub(from_end))),
+            ),
+            None => (Vector2(0, 0), None),
+        }
+    }
+
+    /// replace every remaining match
+    pub fn replace_all(&mut self, editor: &mut Editor) -> usize {
+        let (start, end) = self.bounds(editor);
+        let from = self.current.as_ref().map_or(start, |c| c.start);
+        let found = match &self.query {
+            Some(query) => query.find_all(
+                editor,
+                Some((
+                    from,
+                    end.unwrap_or_else(|| editor.index_to_location(editor.len_chars())),
+                )),
+            ),
+            None => return 0,
+        };
+        let n = replace_all(editor, &found);
+        self.count += n;
+        self.current = None;
+        self.text = None;
+        n
+    }
+
+    /// find the first match at or after `from` within the range
+    pub fn find_from(&mut self, editor: &Editor, from: Vector2) -> Option<&Replacement> {
+        let (start, end) = self.bounds(editor);
+        let from = from.max(start);
+        let text = self.text.get_or_insert_with(|| editor.to_string());
+        self.current = self
+            .query
+            .as_ref()
+            .and_then(|q| q.find_next(editor, text, from, end));
+        self.current.as_ref()
+    }
+
+    /// replace the current match and move to the next one
+    pub fn accept(&mut self, editor: &mut Editor) -> Option<&Replacement> {
+        let current = self.current.take()?;
+        let (byte_start, byte_end) = (
+            editor.location_to_byte(current.start),
+            editor.location_to_byte(current.end),
+        );
+        let end = editor.replace_range(current.start, current.end, &current.text);
+        self.count += 1;
+
+        // the text inserted may differ from the replacement in its line breaks
+        let inserted: String =
+            editor.copy_range(current.start, end).iter().map(|c| c.char).collect();
+        if let Some(text) = self.text.as_mut() {
+            text.replace_range(byte_start..byte_end, &inserted);
+            // a line break before the match may have changed too, then the text is read again
+            if text.len() != editor.len_bytes() {
+                self.text = None;
+            }
+        }
+        self.find_after(editor, current.start, end)
+    }
+
+    /// leave the current match as it is and move to the next one
+    pub fn skip(&mut self, editor: &Editor) -> Option<&Replacement> {
+        let current = self.current.take()?;
+        self.find_after(editor, current.start, current.end)
+    }
+
+    // continue after `end`, stepping over empty matches so they aren't found again
+    fn find_after(
+        &mut self,
+        editor: &Editor,
+        start: Vector2,
+        end: Vector2,
+    ) -> Option<&Replacement> {
+        let next = if end == start {
+            editor.index_to_location(editor.location_to_index(end) + 1)
+        } else {
+            end
+        };
+        if next == end && end == start {
+            // the end of the buffer was reached
+            return None;
+        }
+        self.find_from(editor, next)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_find() {
+        let editor = Editor::from("Hello world\nhello again\nbye");
+        let query = Query::from("hello");
+
+        // forward from the start of the buffer finds the first match, ignoring case
+        let m = query.find(&editor, Vector2(0, 0), Direction::Forward).unwrap();
+        assert_eq!(m.start, Vector2(0, 0));
+        assert_eq!(m.end, Vector2(5, 0));
+
+        let m = query.find(&editor, Vector2(1, 0), Direction::Forward).unwrap();
+        assert_eq!(m.start, Vector2(0, 1));
+
+        // wrap around the end of the buffer
+        let m = query.find(&editor, Vector2(1, 1), Direction::Forward).unwrap();
+        assert_eq!(m.start, Vector2(0, 0));
+
+        let m = query.find(&editor, Vector2(0, 1), Direction::Backward).unwrap();
+        assert_eq!(m.start, Vector2(0, 0));
+        let m = query.find(&editor, Vector2(0, 0), Direction::Backward).unwrap();
+        assert_eq!(m.start, Vector2(0, 1));
+
+        // an uppercase letter makes the search case sensitive
+        let query = Query::from("Hello");
+        assert_eq!(query.find_all(&editor, 0..3).len(), 1);
+
+        assert!(Query::from("missing")
+            .find(&editor, Vector2(0, 0), Direction::Forward)
+            .is_none());
+    }
+
+    #[test]
+    fn test_regex_replace() {
+        let mut editor = Editor::from("let a = 1;\nlet b = 2;\nlet c = 3;");
+
+        // capture groups and replacements which add lines
+        let query = RegexQuery::new(r"let (\w) = (\d);", r"const $1: i32 = $2;\n").unwrap();
+        let found = query.find_all(&editor, None);
+        assert_eq!(found.len(), 3);
+        assert_eq!(found[1].start, Vector2(0, 1));
+        assert_eq!(found[1].text, "const b: i32 = 2;\n");
+
+        // only within a range
+        let found = query.find_all(&editor, Some((Vector2(3, 0), Vector2(9, 2))));
+        assert_eq!(found.len(), 1);
+        assert_eq!(replace_all(&mut editor, &found), 1);
+        assert_eq!(
+            editor.to_string(),
+            "let a = 1;\nconst b: i32 = 2;\n\nlet c = 3;"
+        );
+        assert_eq!(editor.line_count(), 4);
+
+        // anchors look past the ends of the range
+        let foobar = Editor::from("foobar\nfoo");
+        let query = RegexQuery::new(r"\bfoo$", "").unwrap();
+        let part = Some((Vector2(0, 0), Vector2(3, 0)));
+        assert!(query.find_all(&foobar, part).is_empty());
+        let text = foobar.to_string();
+        assert!(query.find_next(&foobar, &text, Vector2(0, 0), Some(Vector2(3, 0))).is_none());
+        let found = query.find_all(&foobar, Some((Vector2(0, 0), Vector2(3, 1))));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].start, Vector2(0, 1));
+        let query = RegexQuery::new(r"foo\b", "").unwrap();
+        assert!(query.find_all(&foobar, Some((Vector2(0, 1), Vector2(2, 1)))).is_empty());
+
+        // matches spanning lines are joined
+        let query = RegexQuery::new(r";\n+", "; ").unwrap();
+        let found = query.find_all(&editor, None);
+        replace_all(&mut editor, &found);
+        assert_eq!(
+            editor.to_string(),
+            "let a = 1; const b: i32 = 2; let c = 3;"
+        );
+        assert_eq!(editor.line_count(), 1);
+
+        // both replacements are undone in one step
+        editor.undo();
+        assert_eq!(
+            editor.to_string(),
+            "let a = 1;\nconst b: i32 = 2;\n\nlet c = 3;"
+        );
+    }
+
+    #[test]
+    fn test_replace_session() {
+        let mut editor = Editor::from("aaa\naaa");
+        editor.set_cursor((1, 0));
+        editor.begin_select();
+        editor.set_cursor((2, 1));
+
+        let mut session = ReplaceSession::new(&editor);
+        session.pattern = "a".to_string();
+        session.template = "bb".to_string();
+        session.compile().unwrap();
+
+        assert_eq!(
+            session.find_from(&editor, Vector2(0, 0)).unwrap().start,
+            Vector2(1, 0)
+        );
+        assert_eq!(session.accept(&mut editor).unwrap().start, Vector2(3, 0));
+        assert_eq!(session.skip(&editor).unwrap().start, Vector2(0, 1));
+        assert_eq!(session.accept(&mut editor).unwrap().start, Vector2(2, 1));
+        // the end of the selection follows the text as it is replaced
+        assert!(session.accept(&mut editor).is_none());
+        assert_eq!(editor.to_string(), "abba\nbbbba");
+        assert_eq!(session.count, 3);
+
+        // the text kept between matches follows replacements of other lengths and line breaks
+        let mut editor = Editor::from("é;こ;\r\nü;");
+        let mut session = ReplaceSession::new(&editor);
+        session.pattern = ";".to_string();
+        session.template = "\\n".to_string();
+        session.compile().unwrap();
+        assert_eq!(session.find_from(&editor, Vector2(0, 0)).unwrap().start, Vector2(1, 0));
+        assert_eq!(session.accept(&mut editor).unwrap().start, Vector2(1, 1));
+        assert_eq!(session.skip(&editor).unwrap().start, Vector2(1, 2));
+        assert!(session.accept(&mut editor).is_none());
+        assert_eq!(editor.to_string(), "é\r\nこ;\r\nü\r\n");
+    }
+}
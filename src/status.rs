@@ -0,0 +1,165 @@
+//! the status line shown below the panes.
+//! the left side has the file name and the latest message, the right side the cursor
+//! position and information about the buffer. when the line doesn't fit the terminal the
+//! message is shortened first, then the file name.
+
+use crate::buffer::Buffer;
+use crate::line_ending::LineEnding;
+use crate::unicode;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The information shown on the status line
+pub struct StatusLine<'a> {
+    pub name: String,
+    pub modified: bool,
+    /// line and column of the cursor, counting from 1
+    pub line: usize,
+    pub column: usize,
+    pub line_count: usize,
+    /// number of selected characters
+    pub selection: Option<usize>,
+    pub encoding: &'static str,
+    pub line_ending: LineEnding,
+    pub message: &'a str,
+    /// the vi mode, when modal editing is on
+    pub mode: Option<&'static str>,
+    /// the register a macro is being recorded into
+    pub recording: Option<char>,
+}
+
+impl<'a> StatusLine<'a> {
+    /// describe `buffer`, along with a message for the user
+    pub fn new(buffer: &Buffer, message: &'a str) -> Self {
+        let editor = &buffer.editor;
+        let cursor = editor.cursor_pos();
+        Self {
+            name: buffer.name(),
+            modified: editor.is_modified(),
+            line: cursor.y() as usize + 1,
+            column: cursor.x() as usize + 1,
+            line_count: editor.line_count(),
+            selection: editor.selection_range().map(|(start, end)| {
+                editor.location_to_index(end) - editor.location_to_index(start)
+            }),
+            // buffers are always read and written as utf-8
+            encoding: "UTF-8",
+            line_ending: editor.line_ending(),
+            message,
+            mode: None,
+            recording: None,
+        }
+    }
+
+    /// lay the status line out to exactly `width` terminal columns
+    pub fn format(&self, width: usize) -> String {
+        let mut right = format!(
+            "Ln {}, Col {} | {} lines",
+            self.line, self.column, self.line_count
+        );
+        if let Some(selected) = self.selection {
+            right += &format!(" | {} selected", selected);
+        }
+        right += &format!(" | {} | {} ", self.encoding, self.line_ending);
+
+        let mode = self.mode.map_or(String::new(), |mode| format!(" {} |", mode));
+        let recording = self
+            .recording
+            .map_or(String::new(), |name| format!(" recording @{} |", name));
+        let name = format!(
+            "{}{} {}{}",
+            mode,
+            recording,
+            self.name,
+            if self.modified { " [+]" } else { "" }
+        );
+        let right_width = display_width(&right);
+
+        // the name keeps at least a few columns before the right side is dropped
+        let (name, right) = if display_width(&name) + right_width <= width {
+            (name, right)
+        } else if right_width + 8 <= width {
+            (truncate(&name, width - right_width), right)
+        } else {
+            (truncate(&name, width), String::new())
+        };
+
+        let room = width - display_width(&name) - display_width(&right);
+        let message = if self.message.is_empty() || room < 5 {
+            String::new()
+        } else {
+            format!(" | {}", truncate(self.message, room - 4))
+        };
+
+        let padding = " ".repeat(room - display_width(&message));
+        format!("{}{}{}{}", name, message, padding, right)
+    }
+}
+
+fn display_width(s: &str) -> usize {
+    s.graphemes(true).map(unicode::width).sum()
+}
+
+/// shorten `s` to at most `width` columns, ending it with an ellipsis if it was cut
+fn truncate(s: &str, width: usize) -> String {
+    if display_width(s) <= width {
+        return s.to_string();
+    }
+    let mut result = String::new();
+    let mut used = 0;
+    for g in s.graphemes(true) {
+        let w = unicode::width(g);
+        if used + w + 1 > width {
+            break;
+        }
+        result.push_str(g);
+        used += w;
+    }
+    if width > 0 {
+        result.push('…');
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::editor::Editor;
+
+    #[test]
+    fn test_status_line() {
+        let mut buffer = Buffer::new(Editor::from("hello\nworld"));
+        buffer.editor.set_cursor((1, 0));
+        buffer.editor.begin_select();
+        buffer.editor.set_cursor((2, 1));
+        buffer.editor.write_at((5, 1), '!');
+
+        let status = StatusLine::new(&buffer, "saved");
+        assert_eq!((status.line, status.column, status.line_count), (2, 3, 2));
+        assert_eq!(status.selection, Some(7));
+
+        let line = status.format(80);
+        assert_eq!(display_width(&line), 80);
+        assert!(line.starts_with(" [untitled] [+] | saved "));
+        assert!(line.ends_with(&format!(
+            "Ln 2, Col 3 | 2 lines | 7 selected | UTF-8 | {} ",
+            LineEnding::default()
+        )));
+
+        // the message is shortened before anything else
+        let line = StatusLine::new(&buffer, "a very long message that does not fit").format(76);
+        assert_eq!(display_width(&line), 76);
+        assert!(line.contains(" | a very l… Ln 2"));
+
+        // on a very narrow terminal only the name is left
+        assert_eq!(status.format(8), " [untit…");
+
+        let status = StatusLine {
+            mode: Some("INSERT"),
+            recording: Some('q'),
+            ..status
+        };
+        assert!(status
+            .format(100)
+            .starts_with(" INSERT | recording @q | [untitled] [+] | saved "));
+    }
+}
@@ -0,0 +1,845 @@
+//! syntax highlighting.
+//! each language provides a `Highlighter` which tokenizes one row at a time, carrying a
+//! `State` from the end of one row to the start of the next for constructs such as block
+//! comments which span rows. the results are cached per row, so after an edit only the
+//! changed rows, and the rows after them whose starting state changed, are tokenized again.
+
+use crate::grid::Grid;
+use crossterm::style::Color;
+use std::path::Path;
+
+/// The kind of a highlighted piece of text
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Token {
+    Keyword,
+    Type,
+    String,
+    Number,
+    Comment,
+    /// literals such as `true` and `null`
+    Constant,
+    Macro,
+    Attribute,
+    /// keys of TOML tables and JSON objects
+    Key,
+    Heading,
+    Emphasis,
+    Code,
+    Link,
+    /// markdown list and quote markers
+    Marker,
+}
+
+impl Token {
+    /// return the foreground color the token is drawn with
+    pub fn color(&self) -> Color {
+        match self {
+            Token::Keyword => Color::Magenta,
+            Token::Type => Color::Yellow,
+            Token::String => Color::Green,
+            Token::Number | Token::Constant => Color::Cyan,
+            Token::Comment => Color::DarkGrey,
+            Token::Macro | Token::Attribute => Color::Blue,
+            Token::Key => Color::DarkCyan,
+            Token::Heading => Color::Red,
+            Token::Emphasis => Color::Magenta,
+            Token::Code => Color::Green,
+            Token::Link => Color::Blue,
+            Token::Marker => Color::Yellow,
+        }
+    }
+}
+
+/// A highlighted range of columns within a row
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub token: Token,
+}
+
+/// What is still open at the end of a row
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum State {
+    #[default]
+    Normal,
+    /// inside a block comment, nested `depth` levels deep
+    Comment(u32),
+    /// inside a string which continues on the next row
+    String,
+    /// inside a rust raw string closed by `"` and `hashes` `#`s
+    RawString(u32),
+    /// inside a TOML multi-line string delimited by three `quote`s
+    MultiLineString(char),
+    /// inside a fenced markdown code block
+    CodeBlock,
+}
+
+/// Tokenizes rows of text for a particular language
+pub trait Highlighter {
+    /// push the spans of `line` onto `spans`, starting in `state`.
+    /// returns the state at the end of the line
+    fn highlight_line(&self, line: &[char], state: State, spans: &mut Vec<Span>) -> State;
+}
+
+/// The languages which can be highlighted
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Language {
+    Rust,
+    Toml,
+    Markdown,
+    Json,
+}
+
+impl Language {
+    /// pick a language from the extension of a file name
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "rs" => Some(Language::Rust),
+            "toml" => Some(Language::Toml),
+            "md" | "markdown" => Some(Language::Markdown),
+            "json" => Some(Language::Json),
+            _ => None,
+        }
+    }
+
+    /// return the highlighter for the language
+    pub fn highlighter(&self) -> Box<dyn Highlighter> {
+        match self {
+            Language::Rust => Box::new(RustHighlighter),
+            Language::Toml => Box::new(TomlHighlighter),
+            Language::Markdown => Box::new(MarkdownHighlighter),
+            Language::Json => Box::new(JsonHighlighter),
+        }
+    }
+}
+
+/// The cached highlighting of a row
+#[derive(Clone, Default)]
+struct Row {
+    start: State,
+    end: State,
+    spans: Vec<Span>,
+}
+
+/// Highlighting of a whole buffer, kept up to date as the buffer is edited
+#[derive(Default)]
+pub struct Syntax {
+    language: Option<Language>,
+    highlighter: Option<Box<dyn Highlighter>>,
+    rows: Vec<Row>,
+    // rows in this range changed and have to be tokenized again
+    dirty: Option<(usize, usize)>,
+}
+
+impl Syntax {
+    pub fn new(language: Option<Language>) -> Self {
+        Self {
+            language,
+            highlighter: language.map(|l| l.highlighter()),
+            rows: Vec::new(),
+            dirty: None,
+        }
+    }
+
+    pub fn language(&self) -> Option<Language> {
+        self.language
+    }
+
+    /// tokenize every row of `grid` from scratch
+    pub fn reset(&mut self, grid: &Grid) {
+        self.rows = vec![Row::default(); grid.len()];
+        self.dirty = Some((0, grid.len()));
+        self.update(grid);
+    }
+
+    /// note that the `removed` rows starting at `row` were replaced by `inserted` rows.
+    /// the highlighting is brought up to date by the next call to `update`
+    pub fn edit(&mut self, row: usize, removed: usize, inserted: usize) {
+        if self.highlighter.is_none() {
+            return;
+        }
+        let end = (row + removed).min(self.rows.len());
+        self.rows.splice(
+            row.min(end)..end,
+            std::iter::repeat_n(Row::default(), inserted),
+        );
+
+        let (from, to) = match self.dirty {
+            Some((from, to)) if to > row + removed => (from.min(row), to - removed + inserted),
+            Some((from, _)) => (from.min(row), row + inserted),
+            None => (row, row + inserted),
+        };
+        self.dirty = Some((from, to));
+    }
+
+    /// tokenize the rows changed since the last update. rows after them are only
+    /// tokenized again while the state they start in differs from before
+    pub fn update(&mut self, grid: &Grid) {
+        let highlighter = match &self.highlighter {
+            Some(h) => h,
+            None => return,
+        };
+        let (from, to) = match self.dirty.take() {
+            Some(range) => range,
+            None => return,
+        };
+        self.rows.resize(grid.len(), Row::default());
+
+        for y in from..grid.len() {
+            let start = if y == 0 {
+                State::Normal
+            } else {
+                self.rows[y - 1].end
+            };
+            if y >= to && self.rows[y].start == start {
+                break;
+            }
+
+            let line: Vec<char> = grid.row_text(y).unwrap_or_default().chars().collect();
+            let row = &mut self.rows[y];
+            row.spans.clear();
+            row.start = start;
+            row.end = highlighter.highlight_line(&line, start, &mut row.spans);
+        }
+    }
+
+    /// return the token covering column `x` of row `y`
+    pub fn token_at(&self, x: usize, y: usize) -> Option<Token> {
+        self.rows
+            .get(y)?
+            .spans
+            .iter()
+            .find(|s| x >= s.start && x < s.end)
+            .map(|s| s.token)
+    }
+
+    /// return the spans of row `y`
+    pub fn spans(&self, y: usize) -> &[Span] {
+        self.rows.get(y).map_or(&[], |r| &r.spans)
+    }
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn is_ident(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// return true if `line` has `pattern` at column `i`
+fn starts_with(line: &[char], i: usize, pattern: &str) -> bool {
+    let mut rest = line.get(i..).unwrap_or(&[]).iter();
+    pattern.chars().all(|p| rest.next() == Some(&p))
+}
+
+/// return the end of the word starting at `i`
+fn scan_while(line: &[char], i: usize, f: impl Fn(char) -> bool) -> usize {
+    i + line[i..].iter().take_while(|&&c| f(c)).count()
+}
+
+/// return the end of the number starting at `i`, which may contain digits, letters for
+/// suffixes and exponents, separators and a decimal point
+fn scan_number(line: &[char], i: usize) -> usize {
+    let mut end = i;
+    while end < line.len() {
+        let c = line[end];
+        let decimal = c == '.' && line.get(end + 1).is_some_and(|c| c.is_ascii_digit());
+        let sign = (c == '+' || c == '-')
+            && matches!(line[end - 1], 'e' | 'E')
+            && !starts_with(line, i, "0x");
+        if c.is_alphanumeric() || c == '_' || decimal || sign {
+            end += 1;
+        } else {
+            break;
+        }
+    }
+    end
+}
+
+/// return the end of a string whose contents start at `i`, and whether it was closed.
+/// a backslash escapes the next character when `escapes` is set
+fn scan_string(line: &[char], mut i: usize, quote: char, escapes: bool) -> (usize, bool) {
+    while i < line.len() {
+        match line[i] {
+            '\\' if escapes => i += 2,
+            c if c == quote => return (i + 1, true),
+            _ => i += 1,
+        }
+    }
+    (line.len(), false)
+}
+
+/// Highlights rust source
+pub struct RustHighlighter;
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "type", "unsafe", "use",
+    "where", "while",
+];
+
+const RUST_PRIMITIVES: &[&str] = &[
+    "bool", "char", "str", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64",
+    "i128", "isize", "f32", "f64",
+];
+
+impl RustHighlighter {
+    /// finish a construct which is still open at column `i`.
+    /// returns the end of the construct and the state after it
+    fn resume(&self, line: &[char], mut i: usize, state: State) -> (usize, State) {
+        match state {
+            State::Comment(mut depth) => {
+                while i < line.len() {
+                    if starts_with(line, i, "*/") {
+                        depth -= 1;
+                        i += 2;
+                        if depth == 0 {
+                            return (i, State::Normal);
+                        }
+                    } else if starts_with(line, i, "/*") {
+                        depth += 1;
+                        i += 2;
+                    } else {
+                        i += 1;
+                    }
+                }
+                (i, State::Comment(depth))
+            }
+            State::String => match scan_string(line, i, '"', true) {
+                (end, true) => (end, State::Normal),
+                (end, false) => (end, State::String),
+            },
+            State::RawString(hashes) => {
+                let close: String = std::iter::once('"')
+                    .chain(std::iter::repeat_n('#', hashes as usize))
+                    .collect();
+                while i < line.len() {
+                    if starts_with(line, i, &close) {
+                        return (i + close.len(), State::Normal);
+                    }
+                    i += 1;
+                }
+                (i, state)
+            }
+            _ => (i, State::Normal),
+        }
+    }
+}
+
+impl Highlighter for RustHighlighter {
+    fn highlight_line(&self, line: &[char], state: State, spans: &mut Vec<Span>) -> State {
+        let mut i = 0;
+        let mut push = |start: usize, end: usize, token: Token| {
+            if end > start {
+                spans.push(Span { start, end, token })
+            }
+        };
+
+        if state != State::Normal {
+            let token = if let State::Comment(_) = state {
+                Token::Comment
+            } else {
+                Token::String
+            };
+            let (end, next) = self.resume(line, 0, state);
+            push(0, end, token);
+            if next != State::Normal {
+                return next;
+            }
+            i = end;
+        }
+
+        while i < line.len() {
+            let start = i;
+            let c = line[i];
+            if starts_with(line, i, "//") {
+                push(i, line.len(), Token::Comment);
+                break;
+            } else if starts_with(line, i, "/*") {
+                let (end, next) = self.resume(line, i + 2, State::Comment(1));
+                push(start, end, Token::Comment);
+                if next != State::Normal {
+                    return next;
+                }
+                i = end;
+            } else if c == '"' || (c == 'b' && line.get(i + 1) == Some(&'"')) {
+                let quote = if c == '"' { i } else { i + 1 };
+                let (end, next) = self.resume(line, quote + 1, State::String);
+                push(start, end, Token::String);
+                if next != State::Normal {
+                    return next;
+                }
+                i = end;
+            } else if (c == 'r' || starts_with(line, i, "br")) && {
+                let hashes = scan_while(line, i + if c == 'r' { 1 } else { 2 }, |c| c == '#');
+                line.get(hashes) == Some(&'"')
+            } {
+                let open = i + if c == 'r' { 1 } else { 2 };
+                let quote = scan_while(line, open, |c| c == '#');
+                let (end, next) =
+                    self.resume(line, quote + 1, State::RawString((quote - open) as u32));
+                push(start, end, Token::String);
+                if next != State::Normal {
+                    return next;
+                }
+                i = end;
+            } else if c == '\'' {
+                // a char literal if it closes within a couple of characters, otherwise a lifetime
+                let close = if line.get(i + 1) == Some(&'\\') {
+                    scan_string(line, i + 1, '\'', true)
+                } else {
+                    (i + 3, line.get(i + 2) == Some(&'\''))
+                };
+                if close.1 {
+                    push(start, close.0, Token::String);
+                    i = close.0;
+                } else {
+                    let end = scan_while(line, i + 1, is_ident);
+                    push(start, end, Token::Type);
+                    i = end.max(i + 1);
+                }
+            } else if c.is_ascii_digit() {
+                i = scan_number(line, i);
+                push(start, i, Token::Number);
+            } else if is_ident_start(c) {
+                i = scan_while(line, i, is_ident);
+                let word: String = line[start..i].iter().collect();
+                if line.get(i) == Some(&'!') && line.get(i + 1) != Some(&'=') {
+                    i += 1;
+                    push(start, i, Token::Macro);
+                } else if RUST_KEYWORDS.contains(&word.as_str()) {
+                    push(start, i, Token::Keyword);
+                } else if word == "true" || word == "false" {
+                    push(start, i, Token::Constant);
+                } else if RUST_PRIMITIVES.contains(&word.as_str()) || c.is_uppercase() {
+                    push(start, i, Token::Type);
+                }
+            } else if c == '#' && (line.get(i + 1) == Some(&'[') || starts_with(line, i, "#![")) {
+                // attributes end at the matching bracket
+                let mut depth = 0;
+                i = line.len();
+                for (j, &c) in line.iter().enumerate().skip(start) {
+                    match c {
+                        '[' => depth += 1,
+                        ']' if depth == 1 => {
+                            i = j + 1;
+                            break;
+                        }
+                        ']' => depth -= 1,
+                        _ => {}
+                    }
+                }
+                push(start, i, Token::Attribute);
+            } else {
+                i += 1;
+            }
+        }
+        State::Normal
+    }
+}
+
+/// Highlights TOML documents
+pub struct TomlHighlighter;
+
+impl Highlighter for TomlHighlighter {
+    fn highlight_line(&self, line: &[char], state: State, spans: &mut Vec<Span>) -> State {
+        let mut i = 0;
+        let mut push =
+            |start: usize, end: usize, token: Token| spans.push(Span { start, end, token });
+
+        if let State::MultiLineString(quote) = state {
+            let close: String = std::iter::repeat_n(quote, 3).collect();
+            loop {
+                if i >= line.len() {
+                    push(0, i, Token::String);
+                    return state;
+                } else if quote == '"' && line[i] == '\\' {
+                    i += 2;
+                } else if starts_with(line, i, &close) {
+                    i += 3;
+                    push(0, i, Token::String);
+                    break;
+                } else {
+                    i += 1;
+                }
+            }
+        }
+
+        // table headers take up the whole line
+        let indent = scan_while(line, i, char::is_whitespace);
+        if i == 0 && line.get(indent) == Some(&'[') {
+            let end = line
+                .iter()
+                .rposition(|&c| c == ']')
+                .map_or(line.len(), |e| e + 1);
+            push(indent, end, Token::Type);
+            i = end;
+        }
+
+        while i < line.len() {
+            let start = i;
+            let c = line[i];
+            if c == '#' {
+                push(i, line.len(), Token::Comment);
+                break;
+            } else if starts_with(line, i, "\"\"\"") || starts_with(line, i, "'''") {
+                let close: String = std::iter::repeat_n(c, 3).collect();
+                i += 3;
+                loop {
+                    if i >= line.len() {
+                        push(start, i, Token::String);
+                        return State::MultiLineString(c);
+                    } else if c == '"' && line[i] == '\\' {
+                        i += 2;
+                    } else if starts_with(line, i, &close) {
+                        i += 3;
+                        break;
+                    } else {
+                        i += 1;
+                    }
+                }
+                push(start, i.min(line.len()), Token::String);
+            } else if c == '"' || c == '\'' {
+                i = scan_string(line, i + 1, c, c == '"').0;
+                push(start, i, Token::String);
+            } else if c.is_alphanumeric() || c == '_' || c == '-' || c == '+' {
+                i = scan_while(line, i, |c| c.is_alphanumeric() || "_-+.:".contains(c));
+                let word: String = line[start..i].iter().collect();
+                let rest = scan_while(line, i, char::is_whitespace);
+                if line.get(rest) == Some(&'=') {
+                    push(start, i, Token::Key);
+                } else if word == "true" || word == "false" {
+                    push(start, i, Token::Constant);
+                } else if word.starts_with(|c: char| c.is_ascii_digit() || c == '+' || c == '-')
+                    || word.ends_with("inf")
+                    || word.ends_with("nan")
+                {
+                    push(start, i, Token::Number);
+                }
+            } else {
+                i += 1;
+            }
+        }
+        State::Normal
+    }
+}
+
+/// Highlights JSON documents
+pub struct JsonHighlighter;
+
+impl Highlighter for JsonHighlighter {
+    fn highlight_line(&self, line: &[char], _state: State, spans: &mut Vec<Span>) -> State {
+        let mut i = 0;
+        while i < line.len() {
+            let start = i;
+            let c = line[i];
+            let token = if c == '"' {
+                i = scan_string(line, i + 1, '"', true).0;
+                // a string followed by a colon is an object key
+                let rest = scan_while(line, i, char::is_whitespace);
+                if line.get(rest) == Some(&':') {
+                    Token::Key
+                } else {
+                    Token::String
+                }
+            } else if c == '-' || c.is_ascii_digit() {
+                i = scan_number(line, i + 1);
+                Token::Number
+            } else if c.is_alphabetic() {
+                i = scan_while(line, i, char::is_alphabetic);
+                match line[start..i].iter().collect::<String>().as_str() {
+                    "true" | "false" | "null" => Token::Constant,
+                    _ => continue,
+                }
+            } else {
+                i += 1;
+                continue;
+            };
+            spans.push(Span {
+                start,
+                end: i,
+                token,
+            });
+        }
+        State::Normal
+    }
+}
+
+/// Highlights markdown documents
+pub struct MarkdownHighlighter;
+
+impl MarkdownHighlighter {
+    /// highlight code spans, emphasis and links within a line of text
+    fn inline(&self, line: &[char], mut i: usize, spans: &mut Vec<Span>) {
+        while i < line.len() {
+            let start = i;
+            let c = line[i];
+            let closed = match c {
+                '`' => {
+                    let ticks = scan_while(line, i, |c| c == '`') - i;
+                    let close: String = std::iter::repeat_n('`', ticks).collect();
+                    (i + ticks..line.len())
+                        .find(|&j| starts_with(line, j, &close))
+                        .map(|j| (j + ticks, Token::Code))
+                }
+                // underscores within a word are not emphasis
+                '_' if i > 0 && is_ident(line[i - 1]) => None,
+                '*' | '_' => {
+                    let run = scan_while(line, i, |x| x == c) - i;
+                    let close: String = std::iter::repeat_n(c, run).collect();
+                    // the emphasis has to start and end next to text
+                    (i + run + 1..line.len())
+                        .filter(|&j| !line[j - 1].is_whitespace())
+                        .find(|&j| starts_with(line, j, &close))
+                        .filter(|_| line.get(i + run).is_some_and(|c| !c.is_whitespace()))
+                        .map(|j| (j + run, Token::Emphasis))
+                }
+                '[' => line[i..]
+                    .iter()
+                    .position(|&c| c == ']')
+                    .map(|j| i + j + 1)
+                    .filter(|&j| line.get(j) == Some(&'('))
+                    .and_then(|j| line[j..].iter().position(|&c| c == ')').map(|k| j + k + 1))
+                    .map(|end| (end, Token::Link)),
+                _ => None,
+            };
+
+            match closed {
+                Some((end, token)) => {
+                    spans.push(Span { start, end, token });
+                    i = end;
+                }
+                None => i += 1,
+            }
+        }
+    }
+}
+
+impl Highlighter for MarkdownHighlighter {
+    fn highlight_line(&self, line: &[char], state: State, spans: &mut Vec<Span>) -> State {
+        let indent = scan_while(line, 0, |c| c == ' ');
+        let fence = starts_with(line, indent, "```") || starts_with(line, indent, "~~~");
+
+        if state == State::CodeBlock || fence {
+            spans.push(Span {
+                start: 0,
+                end: line.len(),
+                token: Token::Code,
+            });
+            return match (state, fence) {
+                (State::CodeBlock, true) => State::Normal,
+                (State::Normal, true) => State::CodeBlock,
+                (state, _) => state,
+            };
+        }
+
+        let hashes = scan_while(line, indent, |c| c == '#') - indent;
+        if (1..=6).contains(&hashes) && line.get(indent + hashes).is_none_or(|&c| c == ' ') {
+            spans.push(Span {
+                start: indent,
+                end: line.len(),
+                token: Token::Heading,
+            });
+            return State::Normal;
+        }
+
+        // quote and list markers
+        let marker = match line.get(indent) {
+            Some('>') => Some(indent + 1),
+            Some('-') | Some('*') | Some('+') if line.get(indent + 1) == Some(&' ') => {
+                Some(indent + 1)
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let end = scan_while(line, indent, |c| c.is_ascii_digit());
+                Some(end + 1).filter(|_| matches!(line.get(end), Some('.') | Some(')')))
+            }
+            _ => None,
+        };
+        if let Some(end) = marker {
+            spans.push(Span {
+                start: indent,
+                end,
+                token: Token::Marker,
+            });
+        }
+
+        self.inline(line, marker.unwrap_or(indent), spans);
+        State::Normal
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// return the tokens of every span in `line` along with their text
+    fn tokens(
+        highlighter: &dyn Highlighter,
+        line: &str,
+        state: State,
+    ) -> (Vec<(String, Token)>, State) {
+        let chars: Vec<char> = line.chars().collect();
+        let mut spans = Vec::new();
+        let state = highlighter.highlight_line(&chars, state, &mut spans);
+        let tokens = spans
+            .iter()
+            .map(|s| (chars[s.start..s.end].iter().collect(), s.token))
+            .collect();
+        (tokens, state)
+    }
+
+    fn token(text: &str, token: Token) -> (String, Token) {
+        (text.to_string(), token)
+    }
+
+    #[test]
+    fn test_rust_highlighter() {
+        let rust = RustHighlighter;
+        let (t, state) = tokens(
+            &rust,
+            "pub fn main() -> u32 { println!(\"{}\", 'a'); 10 } // end",
+            State::Normal,
+        );
+        assert_eq!(
+            t,
+            vec![
+                token("pub", Token::Keyword),
+                token("fn", Token::Keyword),
+                token("u32", Token::Type),
+                token("println!", Token::Macro),
+                token("\"{}\"", Token::String),
+                token("'a'", Token::String),
+                token("10", Token::Number),
+                token("// end", Token::Comment),
+            ]
+        );
+        assert_eq!(state, State::Normal);
+
+        // nested block comments and raw strings continue on the next row
+        let (t, state) = tokens(&rust, "x /* a /* b */", State::Normal);
+        assert_eq!(t, vec![token("/* a /* b */", Token::Comment)]);
+        assert_eq!(state, State::Comment(1));
+        let (t, state) = tokens(&rust, "c */ r#\"raw", state);
+        assert_eq!(
+            t,
+            vec![
+                token("c */", Token::Comment),
+                token("r#\"raw", Token::String)
+            ]
+        );
+        assert_eq!(state, State::RawString(1));
+        let (t, state) = tokens(&rust, "\"# &'a str", state);
+        assert_eq!(
+            t,
+            vec![
+                token("\"#", Token::String),
+                token("'a", Token::Type),
+                token("str", Token::Type)
+            ]
+        );
+        assert_eq!(state, State::Normal);
+    }
+
+    #[test]
+    fn test_other_highlighters() {
+        let (t, _) = tokens(&TomlHighlighter, "[package]", State::Normal);
+        assert_eq!(t, vec![token("[package]", Token::Type)]);
+        let (t, _) = tokens(
+            &TomlHighlighter,
+            "name = \"rust-ed\" # comment",
+            State::Normal,
+        );
+        assert_eq!(
+            t,
+            vec![
+                token("name", Token::Key),
+                token("\"rust-ed\"", Token::String),
+                token("# comment", Token::Comment)
+            ]
+        );
+        let (t, state) = tokens(&TomlHighlighter, "a = 1.5e3 b = '''", State::Normal);
+        assert_eq!(
+            t,
+            vec![
+                token("a", Token::Key),
+                token("1.5e3", Token::Number),
+                token("b", Token::Key),
+                token("'''", Token::String)
+            ]
+        );
+        assert_eq!(state, State::MultiLineString('\''));
+        let (t, state) = tokens(&TomlHighlighter, "text''' c = true", state);
+        assert_eq!(
+            t,
+            vec![
+                token("text'''", Token::String),
+                token("c", Token::Key),
+                token("true", Token::Constant)
+            ]
+        );
+        assert_eq!(state, State::Normal);
+
+        let (t, _) = tokens(
+            &JsonHighlighter,
+            "{\"a\": [-1.5, \"b\", null]}",
+            State::Normal,
+        );
+        assert_eq!(
+            t,
+            vec![
+                token("\"a\"", Token::Key),
+                token("-1.5", Token::Number),
+                token("\"b\"", Token::String),
+                token("null", Token::Constant)
+            ]
+        );
+
+        let md = MarkdownHighlighter;
+        assert_eq!(
+            tokens(&md, "## Title", State::Normal).0,
+            vec![token("## Title", Token::Heading)]
+        );
+        let (t, _) = tokens(&md, "- a `code` and **bold** [link](url)", State::Normal);
+        assert_eq!(
+            t,
+            vec![
+                token("-", Token::Marker),
+                token("`code`", Token::Code),
+                token("**bold**", Token::Emphasis),
+                token("[link](url)", Token::Link)
+            ]
+        );
+        assert_eq!(tokens(&md, "```rust", State::Normal).1, State::CodeBlock);
+        assert_eq!(
+            tokens(&md, "# not a heading", State::CodeBlock).0,
+            vec![token("# not a heading", Token::Code)]
+        );
+        assert_eq!(tokens(&md, "```", State::CodeBlock).1, State::Normal);
+    }
+
+    #[test]
+    fn test_syntax_update() {
+        let mut grid = Grid::from("a\n/* b\nc */\nd");
+        let mut syntax = Syntax::new(Some(Language::Rust));
+        syntax.reset(&grid);
+        assert_eq!(syntax.token_at(0, 2), Some(Token::Comment));
+        assert_eq!(syntax.token_at(0, 3), None);
+
+        // closing the comment early changes the rows after it
+        grid.insert_str(crate::editor::Vector2(4, 1), " */");
+        syntax.edit(1, 1, 1);
+        syntax.update(&grid);
+        assert_eq!(syntax.token_at(0, 2), None);
+
+        // splitting a row shifts the rows after it
+        grid.insert_str(crate::editor::Vector2(0, 0), "//\n");
+        syntax.edit(0, 1, 2);
+        syntax.update(&grid);
+        assert_eq!(syntax.token_at(0, 0), Some(Token::Comment));
+        assert_eq!(syntax.token_at(0, 1), None);
+        assert_eq!(syntax.token_at(0, 2), Some(Token::Comment));
+    }
+}
@@ -0,0 +1,192 @@
+//! grapheme cluster and display width helpers.
+//! buffer columns count `char`s, while the cursor steps over whole grapheme clusters and
+//! the screen is laid out in terminal columns. a tab reaches to the next tab stop, so its
+//! width depends on the column it starts at.
+
+use std::borrow::Cow;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// A grapheme cluster within a row
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Cluster {
+    /// column of the first char of the cluster
+    pub start: usize,
+    /// number of chars in the cluster
+    pub len: usize,
+    /// number of terminal columns the cluster occupies
+    pub width: usize,
+}
+
+/// the distance between tab stops unless another is set
+pub const TAB_WIDTH: usize = 4;
+
+/// The kinds of character words are made of. a word is a run of one kind
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CharClass {
+    Space,
+    Word,
+    Punctuation,
+}
+
+pub fn char_class(c: char) -> CharClass {
+    if c.is_whitespace() {
+        CharClass::Space
+    } else if c.is_alphanumeric() || c == '_' {
+        CharClass::Word
+    } else {
+        CharClass::Punctuation
+    }
+}
+
+/// split a row into grapheme clusters
+pub fn clusters(row: &str) -> Vec<Cluster> {
+    let mut start = 0;
+    row.graphemes(true)
+        .map(|g| {
+            let len = g.chars().count();
+            let cluster = Cluster {
+                start,
+                len,
+                width: width(g),
+            };
+            start += len;
+            cluster
+        })
+        .collect()
+}
+
+/// return the number of terminal columns a grapheme cluster occupies.
+/// every cluster takes up at least one column so the cursor can always be placed on it
+pub fn width(g: &str) -> usize {
+    UnicodeWidthStr::width(g).max(1)
+}
+
+/// return the number of terminal columns a grapheme cluster occupies when it starts at
+/// terminal column `col` of its row
+pub fn width_at(g: &str, col: usize, tab_width: usize) -> usize {
+    if g == "\t" {
+        let tab_width = tab_width.max(1);
+        tab_width - col % tab_width
+    } else {
+        width(g)
+    }
+}
+
+/// return the text to draw for a grapheme cluster.
+/// control characters are replaced so they can't move the terminal cursor,
+/// and a lone combining mark is drawn over a space
+pub fn printable(g: &str) -> Cow<'_, str> {
+    if g == "\t" {
+        Cow::Borrowed(" ")
+    } else if g.chars().any(char::is_control) {
+        Cow::Owned("?".repeat(width(g)))
+    } else if UnicodeWidthStr::width(g) == 0 {
+        Cow::Owned(format!(" {}", g))
+    } else {
+        Cow::Borrowed(g)
+    }
+}
+
+/// return true if the row only contains characters which are one cluster and one column each
+pub fn is_simple(row: &str) -> bool {
+    row.bytes().all(|b| (b' '..=b'~').contains(&b))
+}
+
+/// snap a column down to the start of the cluster containing it
+pub fn floor_boundary(row: &str, col: usize) -> usize {
+    if is_simple(row) {
+        return col;
+    }
+    clusters(row)
+        .iter()
+        .take_while(|c| c.start <= col)
+        .last()
+        .map_or(0, |c| if col >= c.start + c.len { col } else { c.start })
+}
+
+/// move a column `steps` clusters to the left (negative) or right (positive).
+/// the result is clamped to the range [0, row length]
+pub fn step(row: &str, col: usize, steps: i32) -> usize {
+    if is_simple(row) {
+        return (col as i64 + steps as i64).max(0).min(row.len() as i64) as usize;
+    }
+    let clusters = clusters(row);
+    let len: usize = clusters.iter().map(|c| c.len).sum();
+
+    // index of the cluster starting at or containing `col`, or one past the end
+    let index = clusters
+        .iter()
+        .position(|c| col < c.start + c.len)
+        .unwrap_or(clusters.len()) as i64;
+    let index = (index + steps as i64).max(0) as usize;
+
+    clusters.get(index).map_or(len, |c| c.start)
+}
+
+/// return the terminal column at which the char column `col` is drawn
+pub fn display_col(row: &str, col: usize, tab_width: usize) -> usize {
+    if is_simple(row) {
+        return col;
+    }
+    let (mut x, mut start) = (0, 0);
+    for g in row.graphemes(true) {
+        let len = g.chars().count();
+        if start + len > col {
+            return x;
+        }
+        x += width_at(g, x, tab_width);
+        start += len;
+    }
+    // columns past the end of the row are one cell wide each
+    x + (col - start)
+}
+
+/// return the char column of the cluster drawn at terminal column `display`
+pub fn col_at_display(row: &str, display: usize, tab_width: usize) -> usize {
+    if is_simple(row) {
+        return display.min(row.len());
+    }
+    let (mut x, mut start) = (0, 0);
+    for g in row.graphemes(true) {
+        x += width_at(g, x, tab_width);
+        if display < x {
+            return start;
+        }
+        start += g.chars().count();
+    }
+    start
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_clusters() {
+        // 'e' followed by a combining acute accent is a single cluster
+        let row = "cafe\u{301} こんにちは";
+        let clusters = clusters(row);
+        assert_eq!(clusters[3], Cluster { start: 3, len: 2, width: 1 });
+        assert_eq!(clusters[5], Cluster { start: 6, len: 1, width: 2 });
+
+        assert_eq!(step(row, 3, 1), 5);
+        assert_eq!(step(row, 5, -1), 3);
+        assert_eq!(step(row, 0, -5), 0);
+        assert_eq!(step(row, 0, 9999), row.chars().count());
+        assert_eq!(floor_boundary(row, 4), 3);
+
+        assert_eq!(display_col(row, 6, TAB_WIDTH), 5);
+        assert_eq!(display_col(row, 7, TAB_WIDTH), 7);
+        assert_eq!(col_at_display(row, 8, TAB_WIDTH), 7);
+        assert_eq!(col_at_display(row, 100, TAB_WIDTH), row.chars().count());
+
+        // a tab reaches to the next tab stop
+        let row = "a\tb\t\tc";
+        assert_eq!(display_col(row, 2, 4), 4);
+        assert_eq!(display_col(row, 4, 4), 8);
+        assert_eq!(display_col(row, 5, 8), 24);
+        assert_eq!(col_at_display(row, 3, 4), 1);
+        assert_eq!(col_at_display(row, 9, 4), 4);
+    }
+}
@@ -0,0 +1,907 @@
+//! vi style modal editing, layered over the normal key bindings.
+//! in normal mode keys are read as commands made of an optional count, an optional operator
+//! (`d`, `c` or `y`) and a motion, such as `3w`, `d2w` or `yy`. insert mode types like the
+//! editor does without vi, and the visual modes select text for an operator to act on.
+//! keys which aren't part of a vi command fall through to the key bindings.
+
+use crate::editor::{Editor, Vector2};
+use crate::keymap;
+use crate::unicode::{self, char_class, CharClass};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Normal,
+    Insert,
+    Visual,
+    VisualLine,
+}
+
+impl Mode {
+    /// return the name of the mode shown on the status line
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Normal => "NORMAL",
+            Mode::Insert => "INSERT",
+            Mode::Visual => "VISUAL",
+            Mode::VisualLine => "VISUAL LINE",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    WordStart,
+    WordBack,
+    WordEnd,
+    LineStart,
+    FirstNonBlank,
+    LineEnd,
+    FirstLine,
+    LastLine,
+}
+
+/// How the text between the cursor and the end of a motion is operated on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Extent {
+    /// up to the end of the motion
+    Exclusive,
+    /// up to and including the character at the end of the motion
+    Inclusive,
+    /// every line from the cursor to the end of the motion
+    Linewise,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Action {
+    Move(Motion),
+    /// apply an operator up to a motion, or to whole lines when there is none
+    Operate(Operator, Option<Motion>),
+    /// enter insert mode, after moving the cursor
+    Insert(char),
+    Put {
+        before: bool,
+    },
+    Undo,
+    Visual(Mode),
+    Repeat,
+}
+
+enum Parse<T> {
+    Incomplete,
+    Invalid,
+    Done(T),
+}
+
+/// split a count off the start of `keys`. a leading 0 is a motion rather than a count
+fn take_count(keys: &[char]) -> (Option<usize>, &[char]) {
+    let digits = keys
+        .iter()
+        .enumerate()
+        .take_while(|&(i, c)| c.is_ascii_digit() && !(i == 0 && *c == '0'))
+        .count();
+    let count = keys[..digits].iter().collect::<String>().parse().ok();
+    (count, &keys[digits..])
+}
+
+fn parse_motion(keys: &[char]) -> Parse<Motion> {
+    let motion = match keys {
+        [] | ['g'] => return Parse::Incomplete,
+        ['g', 'g'] => Motion::FirstLine,
+        ['h'] => Motion::Left,
+        ['l'] | [' '] => Motion::Right,
+        ['k'] => Motion::Up,
+        ['j'] => Motion::Down,
+        ['w'] => Motion::WordStart,
+        ['b'] => Motion::WordBack,
+        ['e'] => Motion::WordEnd,
+        ['0'] => Motion::LineStart,
+        ['^'] => Motion::FirstNonBlank,
+        ['$'] => Motion::LineEnd,
+        ['G'] => Motion::LastLine,
+        _ => return Parse::Invalid,
+    };
+    Parse::Done(motion)
+}
+
+/// read a normal mode command, returning its count and action once it is complete.
+/// in the visual modes an operator acts on the selection straight away
+fn parse(keys: &[char], visual: bool) -> Parse<(Option<usize>, Action)> {
+    let (count, keys) = take_count(keys);
+    let operator = match keys.first() {
+        Some('d') => Some(Operator::Delete),
+        Some('c') => Some(Operator::Change),
+        Some('y') => Some(Operator::Yank),
+        _ => None,
+    };
+
+    if let Some(operator) = operator {
+        if visual {
+            return Parse::Done((count, Action::Operate(operator, None)));
+        }
+        // counts before the operator and before the motion multiply
+        let (motion_count, rest) = take_count(&keys[1..]);
+        let count = match (count, motion_count) {
+            (None, None) => None,
+            (a, b) => Some(a.unwrap_or(1) * b.unwrap_or(1)),
+        };
+        return match rest {
+            [c] if *c == keys[0] => Parse::Done((count, Action::Operate(operator, None))),
+            _ => match parse_motion(rest) {
+                Parse::Done(motion) => {
+                    Parse::Done((count, Action::Operate(operator, Some(motion))))
+                }
+                Parse::Incomplete => Parse::Incomplete,
+                Parse::Invalid => Parse::Invalid,
+            },
+        };
+    }
+
+    let action = match keys {
+        ['x'] => Action::Operate(Operator::Delete, Some(Motion::Right)),
+        ['X'] => Action::Operate(Operator::Delete, Some(Motion::Left)),
+        ['D'] => Action::Operate(Operator::Delete, Some(Motion::LineEnd)),
+        ['C'] => Action::Operate(Operator::Change, Some(Motion::LineEnd)),
+        ['s'] => Action::Operate(Operator::Change, Some(Motion::Right)),
+        ['S'] => Action::Operate(Operator::Change, None),
+        ['Y'] => Action::Operate(Operator::Yank, None),
+        [c @ 'i'] | [c @ 'a'] | [c @ 'I'] | [c @ 'A'] | [c @ 'o'] | [c @ 'O'] => Action::Insert(*c),
+        ['p'] => Action::Put { before: false },
+        ['P'] => Action::Put { before: true },
+        ['u'] => Action::Undo,
+        ['v'] => Action::Visual(Mode::Visual),
+        ['V'] => Action::Visual(Mode::VisualLine),
+        ['.'] => Action::Repeat,
+        _ => {
+            return match parse_motion(keys) {
+                Parse::Done(motion) => Parse::Done((count, Action::Move(motion))),
+                Parse::Incomplete => Parse::Incomplete,
+                Parse::Invalid => Parse::Invalid,
+            }
+        }
+    };
+    Parse::Done((count, action))
+}
+
+fn line_len(editor: &Editor, y: i32) -> i32 {
+    editor
+        .get_line(y)
+        .map_or(0, |row| row.chars().count() as i32)
+}
+
+/// return the location of the first character of line `y` which isn't a space
+fn first_non_blank(editor: &Editor, y: i32) -> Vector2 {
+    let row = editor.get_line(y).unwrap_or_default();
+    Vector2(
+        row.chars().take_while(|c| c.is_whitespace()).count() as i32,
+        y,
+    )
+}
+
+/// return the location one character after `location`, stopping at the end of the line
+fn next_char(editor: &Editor, location: Vector2) -> Vector2 {
+    let row = editor.get_line(location.y()).unwrap_or_default();
+    Vector2(
+        unicode::step(&row, location.x().max(0) as usize, 1) as i32,
+        location.y(),
+    )
+}
+
+/// Steps through the characters of the buffer one at a time.
+/// the end of each line reads as a line break
+struct Scanner<'a> {
+    editor: &'a Editor,
+    line: Vec<char>,
+    x: usize,
+    y: i32,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(editor: &'a Editor, location: Vector2) -> Self {
+        let line: Vec<char> = editor
+            .get_line(location.y())
+            .unwrap_or_default()
+            .chars()
+            .collect();
+        let x = (location.x().max(0) as usize).min(line.len());
+        Self {
+            editor,
+            line,
+            x,
+            y: location.y(),
+        }
+    }
+
+    fn get(&self) -> char {
+        self.line.get(self.x).copied().unwrap_or('\n')
+    }
+
+    fn location(&self) -> Vector2 {
+        Vector2(self.x as i32, self.y)
+    }
+
+    /// true on a line with nothing on it, which vi treats as a word
+    fn on_empty_line(&self) -> bool {
+        self.line.is_empty()
+    }
+
+    fn load(&mut self, y: i32) {
+        self.y = y;
+        self.line = self
+            .editor
+            .get_line(y)
+            .unwrap_or_default()
+            .chars()
+            .collect();
+    }
+
+    /// move to the next character, returning false at the end of the buffer
+    fn forward(&mut self) -> bool {
+        if self.x < self.line.len() {
+            self.x += 1;
+        } else if self.y + 1 < self.editor.line_count() as i32 {
+            self.load(self.y + 1);
+            self.x = 0;
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// move to the previous character, returning false at the start of the buffer
+    fn backward(&mut self) -> bool {
+        if self.x > 0 {
+            self.x -= 1;
+        } else if self.y > 0 {
+            self.load(self.y - 1);
+            self.x = self.line.len();
+        } else {
+            return false;
+        }
+        true
+    }
+}
+
+/// return the start of the next word after `location`
+fn next_word_start(editor: &Editor, location: Vector2) -> Vector2 {
+    let mut s = Scanner::new(editor, location);
+    let start = char_class(s.get());
+    if start != CharClass::Space {
+        while char_class(s.get()) == start {
+            if !s.forward() {
+                return s.location();
+            }
+        }
+    }
+    // skip the space up to the next word, an empty line counts as a word
+    while char_class(s.get()) == CharClass::Space
+        && !(s.on_empty_line() && s.location() != location)
+    {
+        if !s.forward() {
+            break;
+        }
+    }
+    s.location()
+}
+
+/// return the start of the word before `location`, or of the word it is in
+fn previous_word_start(editor: &Editor, location: Vector2) -> Vector2 {
+    let mut s = Scanner::new(editor, location);
+    if !s.backward() {
+        return s.location();
+    }
+    while char_class(s.get()) == CharClass::Space && !s.on_empty_line() {
+        if !s.backward() {
+            return s.location();
+        }
+    }
+    let word = char_class(s.get());
+    if word == CharClass::Space {
+        return s.location();
+    }
+    while s.backward() {
+        if char_class(s.get()) != word {
+            s.forward();
+            break;
+        }
+    }
+    s.location()
+}
+
+/// return the last character of the word after `location`, or of the word it is in
+fn word_end(editor: &Editor, location: Vector2) -> Vector2 {
+    let mut s = Scanner::new(editor, location);
+    if !s.forward() {
+        return s.location();
+    }
+    while char_class(s.get()) == CharClass::Space {
+        if !s.forward() {
+            return s.location();
+        }
+    }
+    let word = char_class(s.get());
+    loop {
+        let end = s.location();
+        if !s.forward() || char_class(s.get()) != word || s.location().y() != end.y() {
+            return end;
+        }
+    }
+}
+
+/// return the last character of the word `location` is in
+fn current_word_end(editor: &Editor, location: Vector2) -> Vector2 {
+    let mut s = Scanner::new(editor, location);
+    let word = char_class(s.get());
+    loop {
+        let end = s.location();
+        if !s.forward() || char_class(s.get()) != word || s.location().y() != end.y() {
+            return end;
+        }
+    }
+}
+
+/// return where `motion` repeated `count` times goes from `from`
+fn apply_motion(
+    editor: &Editor,
+    from: Vector2,
+    motion: Motion,
+    count: Option<usize>,
+) -> (Vector2, Extent) {
+    let n = count.unwrap_or(1) as i32;
+    let Vector2(x, y) = from;
+    let last_line = editor.line_count() as i32 - 1;
+    let repeat =
+        |step: fn(&Editor, Vector2) -> Vector2| (0..n).fold(from, |at, _| step(editor, at));
+    let row = || editor.get_line(y).unwrap_or_default();
+
+    match motion {
+        Motion::Left => (
+            Vector2(unicode::step(&row(), x as usize, -n) as i32, y),
+            Extent::Exclusive,
+        ),
+        Motion::Right => (
+            Vector2(unicode::step(&row(), x as usize, n) as i32, y),
+            Extent::Exclusive,
+        ),
+        Motion::Up | Motion::Down => {
+            let dy = if motion == Motion::Up { -n } else { n };
+            let target = (y + dy).clamp(0, last_line);
+            let column = editor.display_column(from);
+            (
+                Vector2(editor.column_at_display(target, column), target),
+                Extent::Linewise,
+            )
+        }
+        Motion::WordStart => (repeat(next_word_start), Extent::Exclusive),
+        Motion::WordBack => (repeat(previous_word_start), Extent::Exclusive),
+        Motion::WordEnd => (repeat(word_end), Extent::Inclusive),
+        Motion::LineStart => (Vector2(0, y), Extent::Exclusive),
+        Motion::FirstNonBlank => (first_non_blank(editor, y), Extent::Exclusive),
+        Motion::LineEnd => {
+            let target = (y + n - 1).min(last_line);
+            (Vector2(line_len(editor, target), target), Extent::Exclusive)
+        }
+        Motion::FirstLine | Motion::LastLine => {
+            let default = if motion == Motion::FirstLine {
+                0
+            } else {
+                last_line
+            };
+            let target = count.map_or(default, |n| n as i32 - 1).clamp(0, last_line);
+            (first_non_blank(editor, target), Extent::Linewise)
+        }
+    }
+}
+
+/// Text saved by a delete or yank, to be put back with `p`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Register {
+    pub text: String,
+    /// true if the text is whole lines, which are put on lines of their own
+    pub linewise: bool,
+}
+
+/// The state of the modal editing layer
+#[derive(Clone, Debug)]
+pub struct Vi {
+    pub mode: Mode,
+    /// the unnamed register, filled by every delete and yank
+    pub register: Register,
+    // the keys of the command being typed
+    pending: Vec<KeyEvent>,
+    // the keys of the change in progress, including what was typed in insert mode
+    recording: Option<Vec<KeyEvent>>,
+    // the keys of the last complete change, run again by `.`
+    last_change: Vec<KeyEvent>,
+}
+
+impl Default for Vi {
+    fn default() -> Self {
+        Self {
+            mode: Mode::Normal,
+            register: Register::default(),
+            pending: Vec::new(),
+            recording: None,
+            last_change: Vec::new(),
+        }
+    }
+}
+
+impl Vi {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// handle a key
+    ///
+    /// # Returns
+    /// false if the key isn't used by vi and should be handled by the key bindings
+    pub fn handle_key(&mut self, editor: &mut Editor, key: KeyEvent) -> bool {
+        let plain = keymap::typed_char(key).is_some();
+        if self.mode == Mode::Insert {
+            match key.code {
+                KeyCode::Esc => {
+                    self.mode = Mode::Normal;
+                    // the cursor steps back onto the last character typed
+                    if editor.cursor_pos().x() > 0 {
+                        editor.move_cursor((-1, 0));
+                    }
+                    editor.end_group();
+                    if let Some(mut keys) = self.recording.take() {
+                        keys.push(key);
+                        self.last_change = keys;
+                    }
+                    return true;
+                }
+                KeyCode::Char(c) if plain => editor.write(c),
+                KeyCode::Enter => editor.write('\n'),
+                KeyCode::Tab if plain => editor.write('\t'),
+                KeyCode::Backspace => {
+                    editor.delete();
+                }
+                _ => return false,
+            }
+            if let Some(keys) = self.recording.as_mut() {
+                keys.push(key);
+            }
+            return true;
+        }
+
+        match key.code {
+            KeyCode::Esc => {
+                self.pending.clear();
+                if self.mode != Mode::Normal {
+                    self.mode = Mode::Normal;
+                    editor.clear_selection();
+                }
+            }
+            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.pending.clear();
+                editor.redo();
+            }
+            // the command line is left to the application
+            KeyCode::Char(':') if self.mode == Mode::Normal && self.pending.is_empty() => {
+                return false
+            }
+            KeyCode::Char(_) if plain => {
+                self.pending.push(key);
+                let keys: Vec<char> = self
+                    .pending
+                    .iter()
+                    .filter_map(|k| match k.code {
+                        KeyCode::Char(c) => Some(c),
+                        _ => None,
+                    })
+                    .collect();
+                match parse(&keys, self.mode != Mode::Normal) {
+                    Parse::Incomplete => return true,
+                    Parse::Invalid => self.pending.clear(),
+                    Parse::Done((count, action)) => {
+                        let keys = std::mem::take(&mut self.pending);
+                        self.run(editor, count, action, keys);
+                    }
+                }
+            }
+            _ => return false,
+        }
+        if self.mode == Mode::Normal {
+            clamp_normal(editor);
+        }
+        true
+    }
+
+    /// run a complete command, typed as `keys`
+    fn run(
+        &mut self,
+        editor: &mut Editor,
+        count: Option<usize>,
+        action: Action,
+        keys: Vec<KeyEvent>,
+    ) {
+        let cursor = editor.cursor_pos();
+        let visual = matches!(self.mode, Mode::Visual | Mode::VisualLine);
+
+        match action {
+            Action::Move(motion) => {
+                let (target, _) = apply_motion(editor, cursor, motion, count);
+                editor.set_cursor(target);
+            }
+            Action::Operate(operator, _) if visual => {
+                // the operator acts on the selection
+                let (start, end) = editor.selection_range().unwrap_or((cursor, cursor));
+                let linewise = self.mode == Mode::VisualLine;
+                editor.clear_selection();
+                self.mode = Mode::Normal;
+                let end = if linewise {
+                    end
+                } else {
+                    next_char(editor, end)
+                };
+                if operator == Operator::Change {
+                    editor.begin_group();
+                }
+                self.operate(editor, operator, start, end, linewise);
+            }
+            Action::Operate(operator, motion) => {
+                let (start, end, linewise) = match motion {
+                    None => {
+                        // whole lines, counting down from the cursor
+                        let last = (cursor.y() + count.unwrap_or(1) as i32 - 1)
+                            .min(editor.line_count() as i32 - 1);
+                        (Vector2(0, cursor.y()), Vector2(0, last), true)
+                    }
+                    Some(motion) => {
+                        // cw on a word changes to the end of it, rather than up to the next one
+                        let blank = Scanner::new(editor, cursor).get().is_whitespace();
+                        let (target, extent) = if operator == Operator::Change
+                            && motion == Motion::WordStart
+                            && !blank
+                        {
+                            let end = current_word_end(editor, cursor);
+                            let n = count.unwrap_or(1).saturating_sub(1);
+                            let target = (0..n).fold(end, |at, _| word_end(editor, at));
+                            (target, Extent::Inclusive)
+                        } else {
+                            apply_motion(editor, cursor, motion, count)
+                        };
+                        range(editor, cursor, target, extent)
+                    }
+                };
+                // a change and the text typed after it are undone together
+                if operator == Operator::Change {
+                    editor.begin_group();
+                }
+                self.operate(editor, operator, start, end, linewise);
+                if operator != Operator::Yank {
+                    self.record(keys);
+                }
+            }
+            Action::Insert(at) => {
+                let Vector2(x, y) = cursor;
+                editor.begin_group();
+                match at {
+                    'a' => {
+                        editor.set_cursor(next_char(editor, cursor));
+                    }
+                    'I' => {
+                        editor.set_cursor(first_non_blank(editor, y));
+                    }
+                    'A' => {
+                        editor.set_cursor((line_len(editor, y), y));
+                    }
+                    'o' => {
+                        editor.set_cursor((line_len(editor, y), y));
+                        editor.write('\n');
+                    }
+                    'O' => {
+                        editor.set_cursor((0, y));
+                        editor.write('\n');
+                        editor.set_cursor((0, y));
+                    }
+                    _ => {
+                        editor.set_cursor((x, y));
+                    }
+                }
+                self.mode = Mode::Insert;
+                self.record(keys);
+            }
+            Action::Put { before } => {
+                for _ in 0..count.unwrap_or(1) {
+                    self.put(editor, before);
+                }
+                self.last_change = keys;
+            }
+            Action::Undo => {
+                editor.undo();
+            }
+            Action::Visual(mode) => {
+                if self.mode == mode {
+                    self.mode = Mode::Normal;
+                    editor.clear_selection();
+                } else {
+                    if !visual {
+                        editor.begin_select();
+                    }
+                    self.mode = mode;
+                }
+            }
+            Action::Repeat => {
+                // a count given to . replaces the count the change was made with
+                let change = self.last_change.clone();
+                let keys = match count {
+                    Some(count) => {
+                        let digits = change
+                            .iter()
+                            .enumerate()
+                            .take_while(|&(i, k)| match k.code {
+                                KeyCode::Char(c) => c.is_ascii_digit() && !(i == 0 && c == '0'),
+                                _ => false,
+                            })
+                            .count();
+                        let count = count.to_string();
+                        let count = count.chars().map(|c| KeyEvent::from(KeyCode::Char(c)));
+                        count.chain(change[digits..].iter().copied()).collect()
+                    }
+                    None => change,
+                };
+                for key in keys {
+                    self.handle_key(editor, key);
+                }
+            }
+        }
+    }
+
+    /// remember the keys of a change for `.`. a change which enters insert mode is finished
+    /// when insert mode is left
+    fn record(&mut self, keys: Vec<KeyEvent>) {
+        if self.mode == Mode::Insert {
+            self.recording = Some(keys);
+        } else {
+            self.last_change = keys;
+        }
+    }
+
+    /// apply `operator` to the text from `start` to `end`, or to the lines from `start` to `end`
+    fn operate(
+        &mut self,
+        editor: &mut Editor,
+        operator: Operator,
+        start: Vector2,
+        end: Vector2,
+        linewise: bool,
+    ) {
+        let last_line = editor.line_count() as i32 - 1;
+        let (y1, y2) = (start.y(), end.y());
+
+        if !linewise {
+            let text: String = editor
+                .copy_range(start, end)
+                .into_iter()
+                .map(|c| c.char)
+                .collect();
+            self.register = Register {
+                text: normalize(&text),
+                linewise: false,
+            };
+            if operator != Operator::Yank {
+                editor.cut_range(start, end);
+            }
+            editor.set_cursor(start);
+            if operator == Operator::Change {
+                self.mode = Mode::Insert;
+            }
+            return;
+        }
+
+        let line_end = Vector2(line_len(editor, y2), y2);
+        let text: String = editor
+            .copy_range(Vector2(0, y1), line_end)
+            .into_iter()
+            .map(|c| c.char)
+            .collect();
+        self.register = Register {
+            text: normalize(&text) + "\n",
+            linewise: true,
+        };
+        match operator {
+            Operator::Yank => {
+                let x = editor.cursor_pos().x();
+                editor.set_cursor((x, y1));
+            }
+            Operator::Change => {
+                // the lines are emptied, leaving one to type on
+                editor.cut_range(Vector2(0, y1), line_end);
+                editor.set_cursor((0, y1));
+                self.mode = Mode::Insert;
+            }
+            Operator::Delete => {
+                // take a line break along with the lines
+                if y2 < last_line {
+                    editor.cut_range(Vector2(0, y1), Vector2(0, y2 + 1));
+                } else if y1 > 0 {
+                    editor.cut_range(Vector2(line_len(editor, y1 - 1), y1 - 1), line_end);
+                } else {
+                    editor.cut_range(Vector2(0, y1), line_end);
+                }
+                let y = y1.min(editor.line_count() as i32 - 1);
+                editor.set_cursor(first_non_blank(editor, y));
+            }
+        }
+    }
+
+    /// put the register after the cursor, or before it
+    fn put(&mut self, editor: &mut Editor, before: bool) {
+        let register = &self.register;
+        if register.text.is_empty() {
+            return;
+        }
+        let Vector2(x, y) = editor.cursor_pos();
+
+        if register.linewise {
+            let last_line = editor.line_count() as i32 - 1;
+            if before {
+                editor.replace_range((0, y), (0, y), &register.text);
+            } else if y < last_line {
+                editor.replace_range((0, y + 1), (0, y + 1), &register.text);
+            } else {
+                // there is no line after the last, so a line break goes before the text instead
+                let end = Vector2(line_len(editor, y), y);
+                let text = format!("\n{}", register.text.trim_end_matches('\n'));
+                editor.replace_range(end, end, &text);
+            }
+            let line = if before { y } else { y + 1 };
+            editor.set_cursor(first_non_blank(editor, line));
+        } else {
+            let at = if before {
+                Vector2(x, y)
+            } else {
+                next_char(editor, Vector2(x, y))
+            };
+            let end = editor.replace_range(at, at, &register.text);
+            let last = editor.location_to_index(end).saturating_sub(1);
+            editor.set_cursor(editor.index_to_location(last));
+        }
+    }
+}
+
+/// return the text between two locations to operate on, in order
+fn range(editor: &Editor, from: Vector2, to: Vector2, extent: Extent) -> (Vector2, Vector2, bool) {
+    let (start, end) = (from.min(to), from.max(to));
+    match extent {
+        Extent::Linewise => (Vector2(0, start.y()), Vector2(0, end.y()), true),
+        Extent::Inclusive => (start, next_char(editor, end), false),
+        // an exclusive motion ending at the start of a later line stops at the end of the
+        // line before, so dw on the last word of a line leaves the line break
+        Extent::Exclusive if end.x() == 0 && end.y() > start.y() => (
+            start,
+            Vector2(line_len(editor, end.y() - 1), end.y() - 1),
+            false,
+        ),
+        Extent::Exclusive => (start, end, false),
+    }
+}
+
+/// convert line breaks to `\n`
+fn normalize(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// keep the cursor on a character in normal mode, rather than after the end of the line
+fn clamp_normal(editor: &mut Editor) {
+    let Vector2(x, y) = editor.cursor_pos();
+    let row = editor.get_line(y).unwrap_or_default();
+    let len = row.chars().count();
+    if len > 0 && x as usize >= len {
+        editor.set_cursor((unicode::step(&row, len, -1) as i32, y));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn keys(vi: &mut Vi, editor: &mut Editor, text: &str) {
+        for c in text.chars() {
+            let code = match c {
+                '\u{1b}' => KeyCode::Esc,
+                '\t' => KeyCode::Tab,
+                c => KeyCode::Char(c),
+            };
+            vi.handle_key(editor, KeyEvent::from(code));
+        }
+    }
+
+    #[test]
+    fn test_vi_motions() {
+        let editor = Editor::from("let x = foo(1);\n\n  bar baz");
+        let at = |x, y| Vector2(x, y);
+        assert_eq!(next_word_start(&editor, at(0, 0)), at(4, 0));
+        assert_eq!(next_word_start(&editor, at(8, 0)), at(11, 0));
+        // an empty line is a word
+        assert_eq!(next_word_start(&editor, at(14, 0)), at(0, 1));
+        assert_eq!(next_word_start(&editor, at(0, 1)), at(2, 2));
+        assert_eq!(previous_word_start(&editor, at(2, 2)), at(0, 1));
+        assert_eq!(previous_word_start(&editor, at(10, 0)), at(8, 0));
+        assert_eq!(word_end(&editor, at(8, 0)), at(10, 0));
+        assert_eq!(word_end(&editor, at(14, 0)), at(4, 2));
+
+        assert_eq!(
+            apply_motion(&editor, at(5, 0), Motion::LastLine, None).0,
+            at(2, 2)
+        );
+        assert_eq!(
+            apply_motion(&editor, at(5, 2), Motion::FirstLine, None).0,
+            at(0, 0)
+        );
+        assert_eq!(
+            apply_motion(&editor, at(5, 0), Motion::WordStart, Some(2)).0,
+            at(8, 0)
+        );
+    }
+
+    #[test]
+    fn test_vi_operators() {
+        let mut editor = Editor::from("one two three\nfour five\nsix");
+        let mut vi = Vi::new();
+
+        keys(&mut vi, &mut editor, "dw");
+        assert_eq!(editor.to_string(), "two three\nfour five\nsix");
+        assert_eq!(vi.register.text, "one ");
+
+        // counts multiply, and dw leaves the line break
+        keys(&mut vi, &mut editor, "2d1w");
+        assert_eq!(editor.to_string(), "\nfour five\nsix");
+
+        keys(&mut vi, &mut editor, "jyyGp");
+        assert_eq!(editor.to_string(), "\nfour five\nsix\nfour five");
+        assert_eq!(editor.cursor_pos(), Vector2(0, 3));
+
+        keys(&mut vi, &mut editor, "ggdG");
+        assert_eq!(editor.to_string(), "");
+
+        // change and repeat
+        let mut editor = Editor::from("a b c d");
+        keys(&mut vi, &mut editor, "cwx\u{1b}w.");
+        assert_eq!(editor.to_string(), "x x c d");
+        // a count replaces the one the change was made with
+        keys(&mut vi, &mut editor, "w2.");
+        assert_eq!(editor.to_string(), "x x x");
+        keys(&mut vi, &mut editor, "0D");
+        assert_eq!(editor.to_string(), "");
+        keys(&mut vi, &mut editor, "u");
+        assert_eq!(editor.to_string(), "x x x");
+
+        // the tab key types a tab in insert mode
+        keys(&mut vi, &mut editor, "0i\t\u{1b}");
+        assert_eq!(editor.to_string(), "\tx x x");
+    }
+
+    #[test]
+    fn test_vi_visual() {
+        let mut editor = Editor::from("first\nsecond\nthird");
+        let mut vi = Vi::new();
+
+        keys(&mut vi, &mut editor, "lvlly");
+        assert_eq!(vi.register.text, "irs");
+        assert_eq!(vi.mode, Mode::Normal);
+
+        keys(&mut vi, &mut editor, "jVjd");
+        assert_eq!(editor.to_string(), "first");
+        keys(&mut vi, &mut editor, "P");
+        assert_eq!(editor.to_string(), "second\nthird\nfirst");
+        keys(&mut vi, &mut editor, "A!\u{1b}");
+        assert_eq!(editor.to_string(), "second!\nthird\nfirst");
+        assert_eq!(vi.mode, Mode::Normal);
+    }
+}
@@ -0,0 +1,312 @@
+//! soft wrapping. with wrapping on, each line of the buffer is drawn over as many screen rows
+//! as it needs and the view never scrolls sideways. the x of the view then counts the screen
+//! rows of line `view.y()` scrolled off the top, so the view can start partway into a long line.
+
+use crate::editor::{Editor, Vector2};
+use crate::renderer::Rect;
+use crate::unicode;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A screen row showing part of a line of the buffer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VisualRow {
+    pub y: i32,
+    /// the char columns of the line drawn on this row
+    pub start: i32,
+    pub end: i32,
+}
+
+/// split `row` into the char column ranges drawn on each screen row of `width` columns.
+/// a grapheme cluster is never split between rows. when the last part fills the whole width
+/// an empty part follows it, so the cursor can be placed at the end of the line.
+/// tabs reach to the tab stops counted from the start of the line
+pub fn segments(row: &str, width: i32, tab_width: usize) -> Vec<(i32, i32)> {
+    let width = width.max(1);
+    let mut segments = Vec::new();
+    let (mut start, mut column, mut filled, mut display) = (0, 0, 0, 0);
+    for g in row.graphemes(true) {
+        let w = unicode::width_at(g, display, tab_width) as i32;
+        display += w as usize;
+        if filled > 0 && filled + w > width {
+            segments.push((start, column));
+            start = column;
+            filled = 0;
+        }
+        filled += w;
+        column += g.chars().count() as i32;
+    }
+    segments.push((start, column));
+    if filled >= width {
+        segments.push((column, column));
+    }
+    segments
+}
+
+fn line_segments(editor: &Editor, y: i32, width: i32) -> Vec<(i32, i32)> {
+    segments(&editor.get_line(y).unwrap_or_default(), width, editor.tab_width())
+}
+
+/// return the number of screen rows line `y` is drawn over
+fn row_count(editor: &Editor, y: i32, width: i32) -> i32 {
+    line_segments(editor, y, width).len() as i32
+}
+
+/// return the index of the segment containing char column `x`.
+/// the end of a segment belongs to the one after it
+fn segment_of(segments: &[(i32, i32)], x: i32) -> usize {
+    segments.iter().rposition(|&(start, _)| start <= x).unwrap_or(0)
+}
+
+/// return the screen rows of `view`. rows past the end of the buffer are empty
+pub fn visible_rows(editor: &Editor, view: Rect) -> Vec<VisualRow> {
+    let height = view.height.max(0) as usize;
+    let mut rows = Vec::with_capacity(height);
+    let mut skip = view.x().max(0) as usize;
+    let mut y = view.y();
+
+    while rows.len() < height {
+        if y < 0 || y >= editor.line_count() as i32 {
+            rows.push(VisualRow { y, start: 0, end: 0 });
+        } else {
+            let segments = line_segments(editor, y, view.width);
+            for &(start, end) in segments.iter().skip(skip).take(height - rows.len()) {
+                rows.push(VisualRow { y, start, end });
+            }
+        }
+        skip = 0;
+        y += 1;
+    }
+    rows
+}
+
+/// return the position of `location` on the screen relative to the top left of `view`.
+/// rows above the view are negative. every line between the two is measured, so `location`
+/// should be close to the view
+fn position(editor: &Editor, view: Rect, location: Vector2) -> Vector2 {
+    let Vector2(x, y) = location;
+    let segments = line_segments(editor, y, view.width);
+    let i = segment_of(&segments, x);
+
+    let mut row = i as i32 - view.x();
+    if y >= view.y() {
+        row += (view.y()..y).map(|line| row_count(editor, line, view.width)).sum::<i32>();
+    } else {
+        row -= (y..view.y()).map(|line| row_count(editor, line, view.width)).sum::<i32>();
+    }
+    let column = editor.display_column(location) - editor.display_column(Vector2(segments[i].0, y));
+    Vector2(column, row)
+}
+
+/// return the position of `location` on the screen relative to the top left of `view`,
+/// or none if it is out of view
+pub fn locate(editor: &Editor, view: Rect, location: Vector2) -> Option<Vector2> {
+    // every line takes up at least one row
+    if location.y() < view.y() || location.y() > view.y() + view.x() + view.height {
+        return None;
+    }
+    let position = position(editor, view, location);
+    if position.y() < 0 || position.y() >= view.height {
+        return None;
+    }
+    Some(position)
+}
+
+/// return the location drawn at screen `column` of `row`, staying on that row
+fn location_in(editor: &Editor, row: VisualRow, column: i32) -> Vector2 {
+    let line = editor.get_line(row.y).unwrap_or_default();
+    let base = editor.display_column(Vector2(row.start, row.y));
+    let mut x = editor.column_at_display(row.y, base + column.max(0));
+    // only the last row of a line can have the cursor after its end
+    if x >= row.end && (row.end as usize) < line.chars().count() {
+        x = unicode::step(&line, row.end as usize, -1) as i32;
+    }
+    Vector2(x.min(row.end), row.y)
+}
+
+/// return the location drawn at `position`, relative to the top left of `view`
+pub fn location_at(editor: &Editor, view: Rect, position: Vector2) -> Vector2 {
+    let rows = visible_rows(editor, view);
+    match rows.get(position.y().max(0) as usize).or_else(|| rows.last()) {
+        Some(&row) => location_in(editor, row, position.x()),
+        None => Vector2(0, view.y()),
+    }
+}
+
+/// move `location` `dy` screen rows up (negative) or down, keeping it in the same screen column
+/// where possible
+pub fn move_vertically(editor: &Editor, location: Vector2, dy: i32, width: i32) -> Vector2 {
+    let Vector2(x, mut y) = location;
+    let mut segments = line_segments(editor, y, width);
+    let mut i = segment_of(&segments, x);
+    let column = editor.display_column(location) - editor.display_column(Vector2(segments[i].0, y));
+    let last_line = editor.line_count() as i32 - 1;
+
+    for _ in 0..dy.abs() {
+        if dy > 0 && i + 1 < segments.len() {
+            i += 1;
+        } else if dy > 0 && y < last_line {
+            y += 1;
+            segments = line_segments(editor, y, width);
+            i = 0;
+        } else if dy < 0 && i > 0 {
+            i -= 1;
+        } else if dy < 0 && y > 0 {
+            y -= 1;
+            segments = line_segments(editor, y, width);
+            i = segments.len() - 1;
+        } else {
+            break;
+        }
+    }
+
+    let (start, end) = segments[i];
+    location_in(editor, VisualRow { y, start, end }, column)
+}
+
+/// scroll `view` by `rows` screen rows, stopping at the start and end of the buffer
+pub fn scroll(editor: &Editor, view: &mut Rect, rows: i32) {
+    let last_line = editor.line_count() as i32 - 1;
+    for _ in 0..rows.abs() {
+        let count = line_segments(editor, view.y(), view.width).len() as i32;
+        if rows > 0 && view.x() + 1 < count {
+            view.location.0 += 1;
+        } else if rows > 0 && view.y() < last_line {
+            view.location = Vector2(0, view.y() + 1);
+        } else if rows < 0 && view.x() > 0 {
+            view.location.0 -= 1;
+        } else if rows < 0 && view.y() > 0 {
+            let y = view.y() - 1;
+            view.location = Vector2(line_segments(editor, y, view.width).len() as i32 - 1, y);
+        } else {
+            break;
+        }
+    }
+}
+
+/// scroll `view` so that `location` is visible, centering it if it was out of view
+pub fn reveal(editor: &Editor, view: &mut Rect, location: Vector2) {
+    if locate(editor, *view, location).is_some() {
+        return;
+    }
+    let segments = line_segments(editor, location.y(), view.width);
+    view.location = Vector2(segment_of(&segments, location.x()) as i32, location.y());
+    scroll(editor, view, -(view.height / 2));
+}
+
+/// return the top of the view furthest into the buffer, which has the last row at the bottom
+fn max_top(editor: &Editor, view: Rect) -> Vector2 {
+    let last_line = editor.line_count() as i32 - 1;
+    let mut top = Rect {
+        location: Vector2(row_count(editor, last_line, view.width) - 1, last_line),
+        ..view
+    };
+    scroll(editor, &mut top, -(view.height - 1));
+    top.location
+}
+
+/// keep `view` within the buffer
+pub fn clamp(editor: &Editor, view: &mut Rect) {
+    if view.y() < 0 {
+        view.location = Vector2(0, 0);
+    }
+    let rows = row_count(editor, view.y(), view.width);
+    view.location.0 = view.x().clamp(0, rows - 1);
+
+    let max = max_top(editor, *view);
+    if (view.y(), view.x()) > (max.y(), max.x()) {
+        view.location = max;
+    }
+}
+
+/// scroll `view` as little as possible to keep `location` `margin` rows away from its top and
+/// bottom, then keep the view within the buffer
+pub fn follow(editor: &Editor, view: &mut Rect, location: Vector2, margin: i32) {
+    // start from the location when it's far away, rather than measuring every line in between
+    if location.y() < view.y() || location.y() > view.y() + view.height {
+        reveal(editor, view, location);
+    }
+    let row = position(editor, *view, location).y();
+    if row < margin {
+        scroll(editor, view, row - margin);
+    } else if row > view.height - 1 - margin {
+        scroll(editor, view, row - (view.height - 1 - margin));
+    }
+    clamp(editor, view);
+}
+
+/// return `location` moved into `view`, `margin` rows away from the top and bottom unless the
+/// view can't scroll any further that way
+pub fn constrain(editor: &Editor, view: Rect, location: Vector2, margin: i32) -> Vector2 {
+    let Vector2(column, row) = position(editor, view, location);
+    let top = if view.location != Vector2(0, 0) { margin } else { 0 };
+    let bottom = if view.location != max_top(editor, view) {
+        view.height - 1 - margin
+    } else {
+        view.height - 1
+    };
+    if row < top || row > bottom {
+        location_at(editor, view, Vector2(column, row.clamp(top, bottom.max(top))))
+    } else {
+        location
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_segments() {
+        assert_eq!(segments("", 4, unicode::TAB_WIDTH), vec![(0, 0)]);
+        assert_eq!(segments("abcdefghij", 4, unicode::TAB_WIDTH), vec![(0, 4), (4, 8), (8, 10)]);
+        // a full last row is followed by an empty one for the cursor
+        assert_eq!(segments("abcd", 4, unicode::TAB_WIDTH), vec![(0, 4), (4, 4)]);
+        // wide characters and clusters are kept whole
+        assert_eq!(segments("aこんe\u{301}", 4, unicode::TAB_WIDTH), vec![(0, 2), (2, 5)]);
+    }
+
+    #[test]
+    fn test_wrapped_view() {
+        let editor = Editor::from("abcdefghij\nxy\nlast line");
+        let mut view = Rect {
+            location: Vector2(0, 0),
+            width: 4,
+            height: 3,
+        };
+
+        let rows = visible_rows(&editor, view);
+        assert_eq!(rows[2], VisualRow { y: 0, start: 8, end: 10 });
+        assert_eq!(locate(&editor, view, Vector2(5, 0)), Some(Vector2(1, 1)));
+        assert_eq!(locate(&editor, view, Vector2(0, 1)), None);
+        assert_eq!(location_at(&editor, view, Vector2(3, 2)), Vector2(10, 0));
+        // clicking past the end of a row which continues goes to its last character
+        assert_eq!(location_at(&editor, view, Vector2(6, 0)), Vector2(3, 0));
+
+        // moving down goes through the rows of the line before the next line
+        assert_eq!(move_vertically(&editor, Vector2(1, 0), 1, 4), Vector2(5, 0));
+        assert_eq!(move_vertically(&editor, Vector2(9, 0), 1, 4), Vector2(1, 1));
+        assert_eq!(move_vertically(&editor, Vector2(1, 1), -1, 4), Vector2(9, 0));
+
+        scroll(&editor, &mut view, 2);
+        assert_eq!(view.location, Vector2(2, 0));
+        assert_eq!(locate(&editor, view, Vector2(0, 1)), Some(Vector2(0, 1)));
+        // the view can't scroll past the last row
+        let mut end = Rect {
+            location: Vector2(2, 2),
+            ..view
+        };
+        clamp(&editor, &mut end);
+        assert_eq!(end.location, Vector2(0, 2));
+        assert_eq!(max_top(&editor, view), Vector2(0, 2));
+
+        // following the cursor keeps it a row away from the edges
+        follow(&editor, &mut view, Vector2(1, 2), 1);
+        assert_eq!(view.location, Vector2(0, 1));
+        assert_eq!(constrain(&editor, view, Vector2(0, 1), 1), Vector2(0, 2));
+
+        // revealing a row out of view brings it to the middle
+        reveal(&editor, &mut view, Vector2(8, 2));
+        assert_eq!(view.location, Vector2(1, 2));
+        assert_eq!(locate(&editor, view, Vector2(8, 2)), Some(Vector2(0, 1)));
+    }
+}